@@ -0,0 +1,1314 @@
+//! # Date strings
+//!
+//! Parse free-form date strings in the style of GNU `date -d`, e.g.
+//! `yesterday 14:00`, `2 hours ago`, `next friday`, `@1700000000` or
+//! `2024-05-01 12:00:00 UTC`, resolved against a reference "now".
+// Imports. -------------------------------------------------------------------
+use chrono::{
+  DateTime, Datelike, Duration, FixedOffset, LocalResult, NaiveDate,
+  NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use std::io::{Error, ErrorKind};
+
+// Tokens. --------------------------------------------------------------------
+
+/// A number as it appeared in the input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Number {
+  /// The value of the integer part, with any sign applied.
+  value: i64,
+  /// The number of digits in the integer part.
+  digits: usize,
+  /// The fractional part in nanoseconds, if a fraction was given.
+  nanos: Option<u32>,
+  /// Whether the number was written with a leading `-`.
+  negative: bool,
+}
+
+impl Number {
+  /// ## The fractional part in nanoseconds, with the number's sign.
+  fn signed_nanos(&self) -> Option<i64> {
+    let nanos = self.nanos? as i64;
+    Some(if self.negative { -nanos } else { nanos })
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+  /// A number without a sign, e.g. `2024`.
+  Unsigned(Number),
+  /// A number with a leading sign, e.g. `-05` or `+3`.
+  Signed(Number),
+  /// A lower-cased word with any dots removed, e.g. `am` for `a.m.`.
+  Word(String),
+  /// Any other character, e.g. `:` or `/`.
+  Char(char),
+}
+
+// Parsed items. --------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, Default)]
+struct TimeOfDay {
+  hour: u32,
+  minute: u32,
+  second: u32,
+  nanos: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Relative {
+  years: i64,
+  months: i64,
+  days: i64,
+  hours: i64,
+  minutes: i64,
+  seconds: i64,
+  nanos: i64,
+}
+
+#[derive(Debug, Default)]
+struct Items {
+  year: Option<Number>,
+  month: Option<u32>,
+  day: Option<u32>,
+  time: Option<TimeOfDay>,
+  /// The time zone offset in seconds east of UTC.
+  zone: Option<i32>,
+  /// The day ordinal and the day of the week, e.g. `(1, Fri)` for
+  /// `next friday`.
+  weekday: Option<(i64, Weekday)>,
+  relative: Relative,
+  relative_seen: bool,
+  /// Seconds since the epoch, from the `@SECONDS` form.
+  epoch: Option<Number>,
+}
+
+// Word tables. ---------------------------------------------------------------
+
+/// The relative units by their singular names. Plurals such as `days` are
+/// accepted by stripping the trailing `s`.
+const UNITS: [(&str, Unit); 10] = [
+  ("year", Unit::Year),
+  ("month", Unit::Month),
+  ("fortnight", Unit::Day(14)),
+  ("week", Unit::Day(7)),
+  ("day", Unit::Day(1)),
+  ("hour", Unit::Hour),
+  ("minute", Unit::Minute),
+  ("min", Unit::Minute),
+  ("second", Unit::Second),
+  ("sec", Unit::Second),
+];
+
+/// The ordinal words, as used in `next week` or `third monday`. `second` is
+/// deliberately missing, as it is always read as the unit.
+const ORDINALS: [(&str, i64); 14] = [
+  ("last", -1),
+  ("this", 0),
+  ("next", 1),
+  ("first", 1),
+  ("third", 3),
+  ("fourth", 4),
+  ("fifth", 5),
+  ("sixth", 6),
+  ("seventh", 7),
+  ("eighth", 8),
+  ("ninth", 9),
+  ("tenth", 10),
+  ("eleventh", 11),
+  ("twelfth", 12),
+];
+
+/// The time zone abbreviations, as (name, offset in minutes east of UTC).
+const ZONES: [(&str, i32); 48] = [
+  ("gmt", 0),
+  ("ut", 0),
+  ("utc", 0),
+  ("z", 0),
+  ("wet", 0),
+  ("west", 60),
+  ("bst", 60),
+  ("art", -180),
+  ("brt", -180),
+  ("brst", -120),
+  ("nst", -210),
+  ("ndt", -150),
+  ("ast", -240),
+  ("adt", -180),
+  ("clt", -240),
+  ("clst", -180),
+  ("est", -300),
+  ("edt", -240),
+  ("cst", -360),
+  ("cdt", -300),
+  ("mst", -420),
+  ("mdt", -360),
+  ("pst", -480),
+  ("pdt", -420),
+  ("akst", -540),
+  ("akdt", -480),
+  ("hst", -600),
+  ("hast", -600),
+  ("hadt", -540),
+  ("wat", 60),
+  ("cet", 60),
+  ("cest", 120),
+  ("met", 60),
+  ("mez", 60),
+  ("mest", 120),
+  ("mesz", 120),
+  ("eet", 120),
+  ("eest", 180),
+  ("cat", 120),
+  ("sast", 120),
+  ("eat", 180),
+  ("msk", 180),
+  ("ist", 330),
+  ("sgt", 480),
+  ("kst", 540),
+  ("jst", 540),
+  ("nzst", 720),
+  ("nzdt", 780),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Unit {
+  Year,
+  Month,
+  Day(i64),
+  Hour,
+  Minute,
+  Second,
+}
+
+/// ## Look up a month name.
+///
+/// Full names and three letter abbreviations are accepted, plus `sept`.
+fn month(word: &str) -> Option<u32> {
+  const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+  ];
+  if word == "sept" {
+    return Some(9);
+  }
+  MONTHS
+    .iter()
+    .position(|name| {
+      *name == word || (word.len() == 3 && name.starts_with(word))
+    })
+    .map(|index| index as u32 + 1)
+}
+
+/// ## Look up a day of the week.
+///
+/// Full names and three letter abbreviations are accepted, plus `tues`,
+/// `wednes`, `thur` and `thurs`.
+fn weekday(word: &str) -> Option<Weekday> {
+  let weekday = match word {
+    "sunday" | "sun" => Weekday::Sun,
+    "monday" | "mon" => Weekday::Mon,
+    "tuesday" | "tue" | "tues" => Weekday::Tue,
+    "wednesday" | "wed" | "wednes" => Weekday::Wed,
+    "thursday" | "thu" | "thur" | "thurs" => Weekday::Thu,
+    "friday" | "fri" => Weekday::Fri,
+    "saturday" | "sat" => Weekday::Sat,
+    _ => return None,
+  };
+  Some(weekday)
+}
+
+/// ## Look up a relative unit, singular or plural.
+fn unit(word: &str) -> Option<Unit> {
+  let singular = word.strip_suffix('s').unwrap_or(word);
+  UNITS
+    .iter()
+    .find(|(name, _)| *name == word || *name == singular)
+    .map(|(_, unit)| *unit)
+}
+
+/// ## Look up an ordinal word.
+fn ordinal(word: &str) -> Option<i64> {
+  ORDINALS
+    .iter()
+    .find(|(name, _)| *name == word)
+    .map(|(_, value)| *value)
+}
+
+/// ## Look up a time zone abbreviation.
+///
+/// ### Returns:
+/// * `Option<i32>` - The offset in seconds east of UTC.
+fn zone(word: &str) -> Option<i32> {
+  ZONES
+    .iter()
+    .find(|(name, _)| *name == word)
+    .map(|(_, minutes)| minutes * 60)
+}
+
+// Functions. -----------------------------------------------------------------
+
+/// ## Parse a date string.
+///
+/// Items that are not given default to the values in `now`, and relative
+/// items such as `+3 days` or `1 week ago` are applied on top of that. A
+/// string without a time zone is interpreted in the zone of `now`, unless it
+/// starts with a rule such as `TZ="Europe/Paris"`.
+///
+/// ### Arguments:
+/// * `input` - The date string to parse.
+/// * `now` - The time relative items are resolved against.
+///
+/// ### Returns:
+/// * `Result<DateTime<FixedOffset>, Error>` - The parsed time.
+pub fn parse_date<Tz: TimeZone>(
+  input: &str,
+  now: &DateTime<Tz>,
+) -> Result<DateTime<FixedOffset>, Error> {
+  let (rule, rest) = zone_rule(input);
+  let Some(rule) = rule else {
+    return parse_in_zone(input, rest, now);
+  };
+  match time_zone(&rule) {
+    Some(Zone::Named(zone)) => {
+      parse_in_zone(input, rest, &now.with_timezone(&zone))
+    }
+    Some(Zone::Fixed(zone)) => {
+      parse_in_zone(input, rest, &now.with_timezone(&zone))
+    }
+    None => Err(Error::new(
+      ErrorKind::InvalidInput,
+      format!(
+        "invalid date format '{}': unknown time zone '{}'",
+        input, rule
+      ),
+    )),
+  }
+}
+
+/// ## Parse a date string in the zone of `now`.
+///
+/// ### Arguments:
+/// * `input` - The whole date string, for error messages.
+/// * `rest` - The date string without any `TZ="..."` rule.
+/// * `now` - The time relative items are resolved against.
+///
+/// ### Returns:
+/// * `Result<DateTime<FixedOffset>, Error>` - The parsed time.
+fn parse_in_zone<Tz: TimeZone>(
+  input: &str,
+  rest: &str,
+  now: &DateTime<Tz>,
+) -> Result<DateTime<FixedOffset>, Error> {
+  let invalid = || {
+    Error::new(
+      ErrorKind::InvalidInput,
+      format!("invalid date format '{}'", input),
+    )
+  };
+  let tokens = tokenize(rest).ok_or_else(invalid)?;
+  let mut parser = Parser {
+    tokens,
+    position: 0,
+    items: Items::default(),
+  };
+  parser.parse().ok_or_else(invalid)?;
+  let items = parser.items;
+  if let Some(epoch) = items.epoch {
+    return from_epoch(epoch).ok_or_else(invalid);
+  }
+  let date_time = wall_clock(&items, &now.naive_local()).ok_or_else(invalid)?;
+  let resolved = match items.zone {
+    Some(offset) => FixedOffset::east_opt(offset)
+      .and_then(|zone| zone.from_local_datetime(&date_time).single())
+      .ok_or_else(invalid)?,
+    None => resolve_local(&date_time, now).map_err(|error| {
+      Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid date format '{}': {}", input, error),
+      )
+    })?,
+  };
+  add_elapsed(resolved, &items.relative).ok_or_else(invalid)
+}
+
+/// ## Resolve a local date and time in the zone of `now`.
+///
+/// When clocks go back an hour is repeated, so a time in it is ambiguous; the
+/// offset in effect at `now` is used if it is one of the two, and otherwise
+/// the earlier time. When clocks go forward an hour is skipped, and a time in
+/// it is an error.
+///
+/// ### Arguments:
+/// * `date_time` - The local date and time.
+/// * `now` - The time whose zone and offset are used.
+///
+/// ### Returns:
+/// * `Result<DateTime<FixedOffset>, Error>` - The resolved time.
+pub fn resolve_local<Tz: TimeZone>(
+  date_time: &NaiveDateTime,
+  now: &DateTime<Tz>,
+) -> Result<DateTime<FixedOffset>, Error> {
+  match now.timezone().from_local_datetime(date_time) {
+    LocalResult::Single(resolved) => Ok(resolved.fixed_offset()),
+    LocalResult::Ambiguous(earliest, latest) => {
+      if latest.fixed_offset().offset() == now.fixed_offset().offset() {
+        Ok(latest.fixed_offset())
+      } else {
+        Ok(earliest.fixed_offset())
+      }
+    }
+    LocalResult::None => Err(Error::new(
+      ErrorKind::InvalidInput,
+      format!("{} does not exist in this time zone", date_time),
+    )),
+  }
+}
+
+/// ## Split the input into tokens.
+///
+/// Parenthesised comments are skipped, and a `+` or `-` directly before a
+/// number is read as the number's sign.
+fn tokenize(input: &str) -> Option<Vec<Token>> {
+  let chars: Vec<char> = input.chars().collect();
+  let mut tokens = Vec::new();
+  let mut index = 0;
+  while index < chars.len() {
+    let c = chars[index];
+    if c.is_whitespace() {
+      index += 1;
+    } else if c == '(' {
+      // Comments may nest.
+      let mut depth = 0;
+      loop {
+        match chars.get(index)? {
+          '(' => depth += 1,
+          ')' => depth -= 1,
+          _ => {}
+        }
+        index += 1;
+        if depth == 0 {
+          break;
+        }
+      }
+    } else if c.is_ascii_digit() || is_sign(&chars, index) {
+      let negative = c == '-';
+      if !c.is_ascii_digit() {
+        index += 1;
+        while chars[index].is_whitespace() {
+          index += 1;
+        }
+      }
+      let (number, next) = read_number(&chars, index, negative)?;
+      index = next;
+      if c.is_ascii_digit() {
+        tokens.push(Token::Unsigned(number));
+      } else {
+        tokens.push(Token::Signed(number));
+      }
+    } else if c.is_alphabetic() {
+      let mut word = String::new();
+      while index < chars.len()
+        && (chars[index].is_alphabetic() || chars[index] == '.')
+      {
+        if chars[index] != '.' {
+          word.extend(chars[index].to_lowercase());
+        }
+        index += 1;
+      }
+      tokens.push(Token::Word(word));
+    } else {
+      tokens.push(Token::Char(c));
+      index += 1;
+    }
+  }
+  Some(tokens)
+}
+
+/// ## Whether the character at `index` is a sign followed by a number.
+fn is_sign(chars: &[char], index: usize) -> bool {
+  if chars[index] != '+' && chars[index] != '-' {
+    return false;
+  }
+  chars[index + 1..]
+    .iter()
+    .find(|c| !c.is_whitespace())
+    .is_some_and(|c| c.is_ascii_digit())
+}
+
+/// ## Read a number, with an optional fraction, starting at `index`.
+///
+/// ### Returns:
+/// * `Option<(Number, usize)>` - The number and the index after it.
+fn read_number(
+  chars: &[char],
+  mut index: usize,
+  negative: bool,
+) -> Option<(Number, usize)> {
+  let start = index;
+  let mut value: i64 = 0;
+  while index < chars.len() && chars[index].is_ascii_digit() {
+    let digit = chars[index].to_digit(10)? as i64;
+    value = value.checked_mul(10)?.checked_add(digit)?;
+    index += 1;
+  }
+  let digits = index - start;
+  let mut nanos = None;
+  if index + 1 < chars.len()
+    && (chars[index] == '.' || chars[index] == ',')
+    && chars[index + 1].is_ascii_digit()
+  {
+    index += 1;
+    let mut fraction = 0;
+    let mut scale = 100_000_000;
+    while index < chars.len() && chars[index].is_ascii_digit() {
+      // Digits beyond nanosecond precision are truncated.
+      fraction += chars[index].to_digit(10)? * scale;
+      scale /= 10;
+      index += 1;
+    }
+    nanos = Some(fraction);
+  }
+  let value = if negative { -value } else { value };
+  let number = Number {
+    value,
+    digits,
+    nanos,
+    negative,
+  };
+  Some((number, index))
+}
+
+// Parser. --------------------------------------------------------------------
+
+struct Parser {
+  tokens: Vec<Token>,
+  position: usize,
+  items: Items,
+}
+
+impl Parser {
+  /// ## The token `offset` places after the current one.
+  fn peek(&self, offset: usize) -> Option<&Token> {
+    self.tokens.get(self.position + offset)
+  }
+
+  /// ## The word `offset` places after the current token, if it is one.
+  fn peek_word(&self, offset: usize) -> Option<&str> {
+    match self.peek(offset) {
+      Some(Token::Word(word)) => Some(word),
+      _ => None,
+    }
+  }
+
+  /// ## Whether the token `offset` places ahead is the character `c`.
+  fn peek_char(&self, offset: usize, c: char) -> bool {
+    self.peek(offset) == Some(&Token::Char(c))
+  }
+
+  /// ## Consume and return the next token.
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.position).cloned();
+    self.position += 1;
+    token
+  }
+
+  /// ## Consume the next token if it is an unsigned number.
+  fn unsigned(&mut self) -> Option<Number> {
+    match self.peek(0) {
+      Some(Token::Unsigned(number)) => {
+        let number = *number;
+        self.position += 1;
+        Some(number)
+      }
+      _ => None,
+    }
+  }
+
+  /// ## Parse every item in the input.
+  fn parse(&mut self) -> Option<()> {
+    if self.peek_char(0, '@') {
+      return self.parse_epoch();
+    }
+    while self.position < self.tokens.len() {
+      self.parse_item()?;
+    }
+    Some(())
+  }
+
+  /// ## Parse the `@SECONDS` form, which must be the whole input.
+  fn parse_epoch(&mut self) -> Option<()> {
+    self.position += 1;
+    let number = match self.next()? {
+      Token::Unsigned(number) | Token::Signed(number) => number,
+      _ => return None,
+    };
+    if self.position != self.tokens.len() {
+      return None;
+    }
+    self.items.epoch = Some(number);
+    Some(())
+  }
+
+  /// ## Parse a single item.
+  fn parse_item(&mut self) -> Option<()> {
+    match self.peek(0)?.clone() {
+      Token::Unsigned(number) => self.parse_number_item(number),
+      Token::Signed(number) => {
+        self.position += 1;
+        self.parse_relative(number.value, number.signed_nanos())
+      }
+      Token::Word(word) => self.parse_word_item(&word),
+      Token::Char(',') => {
+        self.position += 1;
+        Some(())
+      }
+      Token::Char(_) => None,
+    }
+  }
+
+  /// ## Parse an item that starts with an unsigned number.
+  fn parse_number_item(&mut self, number: Number) -> Option<()> {
+    if self.peek_char(1, ':') || self.peek_word(1).is_some_and(is_meridian) {
+      return self.parse_time();
+    }
+    if self.peek_char(1, '/') {
+      return self.parse_slash_date();
+    }
+    if let (Some(Token::Signed(month)), Some(Token::Signed(day))) =
+      (self.peek(1), self.peek(2))
+    {
+      if month.negative && day.negative && month.nanos.is_none() {
+        return self.parse_iso_date();
+      }
+    }
+    // The month may be separated from the day by a dash, as in `1-may-2024`.
+    let offset = if self.peek_char(1, '-') { 2 } else { 1 };
+    if let Some(month) = self.peek_word(offset).and_then(month) {
+      return self.parse_day_month(month);
+    }
+    let word = self.peek_word(1).map(str::to_string);
+    if let Some(word) = word {
+      if let Some(weekday) = weekday(&word) {
+        self.position += 2;
+        return self.set_weekday(number.value, weekday);
+      }
+      if unit(&word).is_some() {
+        self.position += 1;
+        return self.parse_relative(number.value, number.signed_nanos());
+      }
+    }
+    self.position += 1;
+    self.parse_bare_number(number)
+  }
+
+  /// ## Parse an item that starts with a word.
+  fn parse_word_item(&mut self, word: &str) -> Option<()> {
+    self.position += 1;
+    if let Some(month) = month(word) {
+      return self.parse_month_day(month);
+    }
+    if let Some(weekday) = weekday(word) {
+      if self.peek_char(0, ',') {
+        self.position += 1;
+      }
+      return self.set_weekday(0, weekday);
+    }
+    if let Some(value) = ordinal(word) {
+      if let Some(weekday) = self.peek_word(0).and_then(weekday) {
+        self.position += 1;
+        return self.set_weekday(value, weekday);
+      }
+      return self.parse_relative(value, None);
+    }
+    if unit(word).is_some() {
+      self.position -= 1;
+      return self.parse_relative(1, None);
+    }
+    match word {
+      "yesterday" => self.add_relative(Unit::Day(1), -1, None),
+      "today" | "now" => self.add_relative(Unit::Day(1), 0, None),
+      "tomorrow" => self.add_relative(Unit::Day(1), 1, None),
+      _ => self.parse_zone(word),
+    }
+  }
+
+  /// ## Parse a time of day, e.g. `14:00`, `2:30:15.5pm` or `2pm`.
+  ///
+  /// A trailing numeric offset such as `+0200` sets the time zone.
+  fn parse_time(&mut self) -> Option<()> {
+    let hour = self.unsigned()?;
+    let mut minute = 0;
+    let mut second = 0;
+    let mut nanos = 0;
+    if hour.nanos.is_some() || hour.digits > 2 {
+      return None;
+    }
+    if self.peek_char(0, ':') {
+      self.position += 1;
+      let number = self.unsigned()?;
+      if number.nanos.is_some() || number.digits > 2 {
+        return None;
+      }
+      minute = number.value as u32;
+      if self.peek_char(0, ':') {
+        self.position += 1;
+        let number = self.unsigned()?;
+        if number.digits > 2 {
+          return None;
+        }
+        second = number.value as u32;
+        nanos = number.nanos.unwrap_or(0);
+      }
+    }
+    let mut hour = hour.value as u32;
+    if let Some(meridian) = self.peek_word(0).filter(|word| is_meridian(word)) {
+      if !(1..=12).contains(&hour) {
+        return None;
+      }
+      hour = hour % 12 + if meridian.starts_with('p') { 12 } else { 0 };
+      self.position += 1;
+    }
+    if hour > 23 || minute > 59 || second > 59 {
+      return None;
+    }
+    if self.items.time.is_some() {
+      return None;
+    }
+    self.items.time = Some(TimeOfDay {
+      hour,
+      minute,
+      second,
+      nanos,
+    });
+    // As with GNU, a signed number directly after a time is always a zone
+    // offset, so `12:00 -1 hour` is noon at UTC-1 plus one hour.
+    if let Some(Token::Signed(number)) = self.peek(0).cloned() {
+      self.position += 1;
+      let offset = self.parse_offset(number)?;
+      return self.set_zone(offset);
+    }
+    Some(())
+  }
+
+  /// ## Parse a numeric zone offset, e.g. `+02`, `-0530` or `+05:30`.
+  ///
+  /// ### Returns:
+  /// * `Option<i32>` - The offset in seconds east of UTC.
+  fn parse_offset(&mut self, number: Number) -> Option<i32> {
+    if number.nanos.is_some() {
+      return None;
+    }
+    let magnitude = number.value.abs();
+    let (hours, minutes) = if self.peek_char(0, ':') {
+      self.position += 1;
+      let minutes = self.unsigned()?;
+      if number.digits > 2 || minutes.digits != 2 || minutes.nanos.is_some() {
+        return None;
+      }
+      (magnitude, minutes.value)
+    } else if number.digits <= 2 {
+      (magnitude, 0)
+    } else if number.digits <= 4 {
+      (magnitude / 100, magnitude % 100)
+    } else {
+      return None;
+    };
+    if hours > 24 || minutes > 59 {
+      return None;
+    }
+    let offset = (hours * 3600 + minutes * 60) as i32;
+    Some(if number.negative { -offset } else { offset })
+  }
+
+  /// ## Parse a date written with slashes, e.g. `5/1` or `5/1/2024`.
+  fn parse_slash_date(&mut self) -> Option<()> {
+    let first = self.unsigned()?;
+    self.position += 1;
+    let second = self.unsigned()?;
+    if self.peek_char(0, '/') {
+      self.position += 1;
+      let third = self.unsigned()?;
+      if first.digits >= 3 {
+        // YYYY/MM/DD
+        return self.set_date(Some(first), second.value, third.value);
+      }
+      return self.set_date(Some(third), first.value, second.value);
+    }
+    self.set_date(None, first.value, second.value)
+  }
+
+  /// ## Parse an ISO 8601 date, e.g. `2024-05-01` or `2024-05-01T12:00`.
+  fn parse_iso_date(&mut self) -> Option<()> {
+    let year = self.unsigned()?;
+    let (Some(Token::Signed(month)), Some(Token::Signed(day))) =
+      (self.next(), self.next())
+    else {
+      return None;
+    };
+    if day.nanos.is_some() {
+      return None;
+    }
+    self.set_date(Some(year), -month.value, -day.value)?;
+    if self.peek_word(0) == Some("t")
+      && matches!(self.peek(1), Some(Token::Unsigned(_)))
+    {
+      self.position += 1;
+      return self.parse_time();
+    }
+    Some(())
+  }
+
+  /// ## Parse a date that starts with the day, e.g. `1 may 2024`.
+  fn parse_day_month(&mut self, month: u32) -> Option<()> {
+    let day = self.unsigned()?;
+    if self.peek_char(0, '-') {
+      self.position += 1;
+    }
+    self.position += 1;
+    let year = self.optional_year();
+    self.set_date(year, month as i64, day.value)
+  }
+
+  /// ## Parse a date that starts with the month, e.g. `may 1, 2024`.
+  fn parse_month_day(&mut self, month: u32) -> Option<()> {
+    if self.peek_char(0, '-') {
+      self.position += 1;
+    }
+    let day = match self.next()? {
+      Token::Unsigned(day) => day,
+      Token::Signed(day) if day.negative => Number {
+        value: -day.value,
+        negative: false,
+        ..day
+      },
+      _ => return None,
+    };
+    if self.peek_char(0, ',') {
+      self.position += 1;
+    }
+    let year = self.optional_year();
+    self.set_date(year, month as i64, day.value)
+  }
+
+  /// ## Consume the year that may follow a day and month.
+  ///
+  /// A number that is followed by `:` or a meridian is left alone, as it is
+  /// the start of a time rather than a year.
+  fn optional_year(&mut self) -> Option<Number> {
+    let year = match self.peek(0)? {
+      Token::Unsigned(year) if !self.starts_time(1) => *year,
+      Token::Signed(year) if year.negative => Number {
+        value: -year.value,
+        negative: false,
+        ..*year
+      },
+      _ => return None,
+    };
+    self.position += 1;
+    Some(year)
+  }
+
+  /// ## Whether the token at `offset` makes the number before it a time.
+  fn starts_time(&self, offset: usize) -> bool {
+    self.peek_char(offset, ':')
+      || self.peek_word(offset).is_some_and(is_meridian)
+  }
+
+  /// ## Parse a bare number.
+  ///
+  /// Following GNU, a number is a year if a date has been seen without one,
+  /// a `YYYYMMDD` date if it has more than four digits, and otherwise a time
+  /// of day as `HH` or `HHMM`.
+  fn parse_bare_number(&mut self, number: Number) -> Option<()> {
+    if number.nanos.is_some() {
+      return None;
+    }
+    let items = &self.items;
+    if items.day.is_some()
+      && items.year.is_none()
+      && !items.relative_seen
+      && (items.time.is_some() || number.digits > 2)
+    {
+      self.items.year = Some(number);
+      return Some(());
+    }
+    if number.digits > 4 {
+      let year = Number {
+        value: number.value / 10000,
+        digits: number.digits - 4,
+        ..number
+      };
+      let month = number.value / 100 % 100;
+      return self.set_date(Some(year), month, number.value % 100);
+    }
+    let (hour, minute) = if number.digits <= 2 {
+      (number.value, 0)
+    } else {
+      (number.value / 100, number.value % 100)
+    };
+    if hour > 23 || minute > 59 || self.items.time.is_some() {
+      return None;
+    }
+    self.items.time = Some(TimeOfDay {
+      hour: hour as u32,
+      minute: minute as u32,
+      ..TimeOfDay::default()
+    });
+    Some(())
+  }
+
+  /// ## Parse a relative item after its count has been consumed.
+  ///
+  /// The next token must be a unit, optionally followed by `ago`.
+  fn parse_relative(&mut self, count: i64, nanos: Option<i64>) -> Option<()> {
+    let unit = self.peek_word(0).and_then(unit)?;
+    self.position += 1;
+    if self.peek_word(0) == Some("ago") {
+      self.position += 1;
+      return self.add_relative(unit, -count, nanos.map(|nanos| -nanos));
+    }
+    self.add_relative(unit, count, nanos)
+  }
+
+  /// ## Add a relative item.
+  ///
+  /// A fraction, in signed nanoseconds, is only accepted on seconds.
+  fn add_relative(
+    &mut self,
+    unit: Unit,
+    count: i64,
+    nanos: Option<i64>,
+  ) -> Option<()> {
+    let relative = &mut self.items.relative;
+    match unit {
+      Unit::Second => {
+        relative.seconds = relative.seconds.checked_add(count)?;
+        relative.nanos += nanos.unwrap_or(0);
+      }
+      _ if nanos.is_some() => return None,
+      Unit::Year => relative.years = relative.years.checked_add(count)?,
+      Unit::Month => relative.months = relative.months.checked_add(count)?,
+      Unit::Day(days) => {
+        relative.days = relative.days.checked_add(count.checked_mul(days)?)?
+      }
+      Unit::Hour => relative.hours = relative.hours.checked_add(count)?,
+      Unit::Minute => relative.minutes = relative.minutes.checked_add(count)?,
+    }
+    self.items.relative_seen = true;
+    Some(())
+  }
+
+  /// ## Parse a time zone abbreviation, with an optional numeric correction.
+  fn parse_zone(&mut self, word: &str) -> Option<()> {
+    let mut offset = zone(word)?;
+    if let Some(Token::Signed(number)) = self.peek(0).cloned() {
+      if self.peek_word(1).is_none_or(|word| unit(word).is_none()) {
+        self.position += 1;
+        offset += self.parse_offset(number)?;
+      }
+    }
+    if self.peek_word(0) == Some("dst") {
+      self.position += 1;
+      offset += 3600;
+    }
+    self.set_zone(offset)
+  }
+
+  /// ## Record the date, rejecting a second date.
+  fn set_date(
+    &mut self,
+    year: Option<Number>,
+    month: i64,
+    day: i64,
+  ) -> Option<()> {
+    if self.items.day.is_some()
+      || !(1..=12).contains(&month)
+      || !(1..=31).contains(&day)
+    {
+      return None;
+    }
+    self.items.year = year;
+    self.items.month = Some(month as u32);
+    self.items.day = Some(day as u32);
+    Some(())
+  }
+
+  /// ## Record the day of the week, rejecting a second one.
+  fn set_weekday(&mut self, ordinal: i64, weekday: Weekday) -> Option<()> {
+    if self.peek_char(0, ',') {
+      self.position += 1;
+    }
+    if self.items.weekday.is_some() {
+      return None;
+    }
+    self.items.weekday = Some((ordinal, weekday));
+    Some(())
+  }
+
+  /// ## Record the time zone, rejecting a second one.
+  fn set_zone(&mut self, offset: i32) -> Option<()> {
+    if self.items.zone.is_some() {
+      return None;
+    }
+    self.items.zone = Some(offset);
+    Some(())
+  }
+}
+
+/// ## Whether a word is `am` or `pm`.
+fn is_meridian(word: &str) -> bool {
+  word == "am" || word == "pm"
+}
+
+// Resolution. ----------------------------------------------------------------
+
+/// ## The local date and time described by the parsed items.
+///
+/// Relative years, months and days and any day of the week are applied, but
+/// not relative hours, minutes and seconds, which are elapsed time.
+fn wall_clock(items: &Items, local: &NaiveDateTime) -> Option<NaiveDateTime> {
+  let year = match items.year {
+    Some(year) if year.digits <= 2 => {
+      year.value + if year.value < 69 { 2000 } else { 1900 }
+    }
+    Some(year) => year.value,
+    None => local.year() as i64,
+  };
+  let month = items.month.unwrap_or(local.month());
+  let day = items.day.unwrap_or(local.day());
+  let date = NaiveDate::from_ymd_opt(i32::try_from(year).ok()?, month, day)?;
+
+  // Following GNU, the time of day is kept from `now` only when nothing but
+  // relative items were given; otherwise it defaults to midnight.
+  let time = match items.time {
+    Some(time) => NaiveTime::from_hms_nano_opt(
+      time.hour,
+      time.minute,
+      time.second,
+      time.nanos,
+    )?,
+    None
+      if items.relative_seen
+        && items.day.is_none()
+        && items.weekday.is_none() =>
+    {
+      local.time()
+    }
+    None => NaiveTime::MIN,
+  };
+  let mut date_time = date.and_time(time);
+
+  // Years, months and days move the calendar date. A day that is past the end
+  // of the new month overflows into the next, as with `mktime`.
+  let relative = &items.relative;
+  let months = (date_time.year() as i64 * 12 + date_time.month0() as i64)
+    .checked_add(relative.years.checked_mul(12)?)?
+    .checked_add(relative.months)?;
+  let first = NaiveDate::from_ymd_opt(
+    i32::try_from(months.div_euclid(12)).ok()?,
+    months.rem_euclid(12) as u32 + 1,
+    1,
+  )?;
+  date_time = NaiveDateTime::new(first, date_time.time()).checked_add_signed(
+    Duration::try_days((day as i64 - 1).checked_add(relative.days)?)?,
+  )?;
+
+  // A day of the week without a date moves forward to that day.
+  if let (Some((ordinal, weekday)), None) = (items.weekday, items.day) {
+    let current = date_time.weekday().num_days_from_sunday() as i64;
+    let target = weekday.num_days_from_sunday() as i64;
+    let skip = if ordinal > 0 && current != target {
+      1
+    } else {
+      0
+    };
+    let weeks = ordinal.checked_sub(skip)?.checked_mul(7)?;
+    let days = ((target - current + 7) % 7).checked_add(weeks)?;
+    date_time = date_time.checked_add_signed(Duration::try_days(days)?)?;
+  }
+
+  Some(date_time)
+}
+
+/// ## Add the relative hours, minutes and seconds to a resolved time.
+fn add_elapsed(
+  resolved: DateTime<FixedOffset>,
+  relative: &Relative,
+) -> Option<DateTime<FixedOffset>> {
+  let elapsed = Duration::try_hours(relative.hours)?
+    .checked_add(&Duration::try_minutes(relative.minutes)?)?
+    .checked_add(&Duration::try_seconds(relative.seconds)?)?
+    .checked_add(&Duration::nanoseconds(relative.nanos))?;
+  resolved.checked_add_signed(elapsed)
+}
+
+/// ## Split a leading `TZ="..."` rule from the rest of the input.
+///
+/// Inside the quotes a backslash escapes the next character.
+///
+/// ### Returns:
+/// * `(Option<String>, &str)` - The rule, if there is one, and the rest.
+fn zone_rule(input: &str) -> (Option<String>, &str) {
+  let Some(quoted) = input.trim_start().strip_prefix("TZ=\"") else {
+    return (None, input);
+  };
+  let mut rule = String::new();
+  let mut chars = quoted.char_indices();
+  while let Some((index, c)) = chars.next() {
+    match c {
+      '"' => return (Some(rule), &quoted[index + 1..]),
+      '\\' => match chars.next() {
+        Some((_, c)) => rule.push(c),
+        None => break,
+      },
+      _ => rule.push(c),
+    }
+  }
+  // An unterminated rule is left for the parser to reject.
+  (None, input)
+}
+
+/// A time zone given by a `TZ="..."` rule.
+enum Zone {
+  /// A zone from the time zone database, e.g. `Europe/Paris`.
+  Named(chrono_tz::Tz),
+  /// A POSIX zone without daylight saving time, e.g. `UTC0`.
+  Fixed(FixedOffset),
+}
+
+/// ## Look up the time zone for a `TZ` rule.
+///
+/// Names from the time zone database are accepted with or without a leading
+/// `:`, as are POSIX rules without daylight saving time, such as `UTC0`,
+/// `EST5` or `<+0330>-3:30`.
+fn time_zone(rule: &str) -> Option<Zone> {
+  let name = rule.strip_prefix(':').unwrap_or(rule);
+  if let Ok(zone) = name.parse::<chrono_tz::Tz>() {
+    return Some(Zone::Named(zone));
+  }
+  // The name is three or more letters, or anything between angle brackets.
+  let offset = match rule.strip_prefix('<') {
+    Some(quoted) => &quoted[quoted.find('>')? + 1..],
+    None => {
+      let end = rule
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rule.len());
+      if end < 3 {
+        return None;
+      }
+      &rule[end..]
+    }
+  };
+  // POSIX offsets are hours west of UTC, as `[+-]hh[:mm[:ss]]`.
+  let (sign, offset) = match offset.strip_prefix('-') {
+    Some(offset) => (-1, offset),
+    None => (1, offset.strip_prefix('+').unwrap_or(offset)),
+  };
+  let mut fields = [0; 3];
+  for (index, field) in offset.split(':').enumerate() {
+    if index >= fields.len()
+      || field.is_empty()
+      || !field.bytes().all(|b| b.is_ascii_digit())
+    {
+      return None;
+    }
+    fields[index] = field.parse().ok()?;
+  }
+  let [hours, minutes, seconds] = fields;
+  if hours > 24 || minutes > 59 || seconds > 59 {
+    return None;
+  }
+  FixedOffset::west_opt(sign * (hours * 3600 + minutes * 60 + seconds))
+    .map(Zone::Fixed)
+}
+
+/// ## Convert seconds since the epoch into a time.
+fn from_epoch(epoch: Number) -> Option<DateTime<FixedOffset>> {
+  let nanos = epoch.signed_nanos().unwrap_or(0);
+  let total = Duration::try_seconds(epoch.value)?
+    .checked_add(&Duration::nanoseconds(nanos))?;
+  DateTime::<Utc>::UNIX_EPOCH
+    .checked_add_signed(total)
+    .map(|date_time| date_time.fixed_offset())
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::SecondsFormat;
+
+  /// ## The time every test resolves against, a Wednesday.
+  fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 5, 15, 10, 30, 45).unwrap()
+  }
+
+  /// ## Parse a date string against `now`, as an RFC 3339 time in UTC.
+  fn parse(input: &str) -> String {
+    parse_date(input, &now())
+      .unwrap_or_else(|error| panic!("{}: {}", input, error))
+      .with_timezone(&Utc)
+      .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+  }
+
+  /// ## Check that each input parses to the expected time.
+  fn check(cases: &[(&str, &str)]) {
+    for (input, expected) in cases {
+      assert_eq!(parse(input), *expected, "{}", input);
+    }
+  }
+
+  #[test]
+  fn relative_items() {
+    check(&[
+      ("now", "2024-05-15T10:30:45Z"),
+      ("today", "2024-05-15T10:30:45Z"),
+      ("yesterday", "2024-05-14T10:30:45Z"),
+      ("tomorrow 14:00", "2024-05-16T14:00:00Z"),
+      ("2 hours ago", "2024-05-15T08:30:45Z"),
+      ("+3 days", "2024-05-18T10:30:45Z"),
+      ("-90 minutes", "2024-05-15T09:00:45Z"),
+      ("1 week ago", "2024-05-08T10:30:45Z"),
+      ("fortnight", "2024-05-29T10:30:45Z"),
+      ("1 year 2 months", "2025-07-15T10:30:45Z"),
+      ("1 month ago", "2024-04-15T10:30:45Z"),
+      ("1.5 seconds ago", "2024-05-15T10:30:43.500Z"),
+      ("2024-01-31 +1 month", "2024-03-02T00:00:00Z"),
+      ("2024-05-01 12:00 UTC +2 hours", "2024-05-01T14:00:00Z"),
+    ]);
+  }
+
+  #[test]
+  fn ordinals_and_weekdays() {
+    check(&[
+      ("friday", "2024-05-17T00:00:00Z"),
+      ("wednesday", "2024-05-15T00:00:00Z"),
+      ("next friday", "2024-05-17T00:00:00Z"),
+      ("last friday", "2024-05-10T00:00:00Z"),
+      ("this wednesday", "2024-05-15T00:00:00Z"),
+      ("next wednesday", "2024-05-22T00:00:00Z"),
+      ("third monday", "2024-06-03T00:00:00Z"),
+      ("next week", "2024-05-22T10:30:45Z"),
+      ("last year", "2023-05-15T10:30:45Z"),
+      ("sat, 1 jun 2024 09:00", "2024-06-01T09:00:00Z"),
+    ]);
+  }
+
+  #[test]
+  fn calendar_dates() {
+    check(&[
+      ("2024-05-01", "2024-05-01T00:00:00Z"),
+      ("2024-05-01T12:34:56.789", "2024-05-01T12:34:56.789Z"),
+      ("5/1", "2024-05-01T00:00:00Z"),
+      ("5/1/99", "1999-05-01T00:00:00Z"),
+      ("2024/12/25", "2024-12-25T00:00:00Z"),
+      ("1 may 2024", "2024-05-01T00:00:00Z"),
+      ("1-may-2024", "2024-05-01T00:00:00Z"),
+      ("may 1, 2024 (a comment)", "2024-05-01T00:00:00Z"),
+      ("june 3", "2024-06-03T00:00:00Z"),
+      ("20240501", "2024-05-01T00:00:00Z"),
+      ("1230", "2024-05-15T12:30:00Z"),
+      ("1969-12-31 23:59:59", "1969-12-31T23:59:59Z"),
+      // As with GNU, an empty string is the start of today.
+      ("", "2024-05-15T00:00:00Z"),
+    ]);
+  }
+
+  #[test]
+  fn meridians() {
+    check(&[
+      ("2pm", "2024-05-15T14:00:00Z"),
+      ("2:30 pm", "2024-05-15T14:30:00Z"),
+      ("12am", "2024-05-15T00:00:00Z"),
+      ("12:15pm", "2024-05-15T12:15:00Z"),
+      ("11:59:59.5 pm", "2024-05-15T23:59:59.500Z"),
+      ("may 1 9am", "2024-05-01T09:00:00Z"),
+    ]);
+  }
+
+  #[test]
+  fn epoch_seconds() {
+    check(&[
+      ("@0", "1970-01-01T00:00:00Z"),
+      ("@1700000000", "2023-11-14T22:13:20Z"),
+      ("@1700000000.5", "2023-11-14T22:13:20.500Z"),
+      ("@-1.5", "1969-12-31T23:59:58.500Z"),
+      ("@1.000000001", "1970-01-01T00:00:01.000000001Z"),
+    ]);
+  }
+
+  #[test]
+  fn zones_and_corrections() {
+    check(&[
+      ("2024-01-01 12:00 UTC", "2024-01-01T12:00:00Z"),
+      ("2024-01-01 12:00 EST", "2024-01-01T17:00:00Z"),
+      ("2024-07-01 12:00 CEST", "2024-07-01T10:00:00Z"),
+      ("2024-01-01 12:00 EST dst", "2024-01-01T16:00:00Z"),
+      ("2024-01-01 12:00 UTC+2", "2024-01-01T10:00:00Z"),
+      ("2024-01-01 12:00 UTC-05:30", "2024-01-01T17:30:00Z"),
+      ("2024-01-01 12:00 +0200", "2024-01-01T10:00:00Z"),
+      ("2024-01-01 12:00 -1 hour", "2024-01-01T14:00:00Z"),
+      ("2024-01-01 12:00 UTC -1 hour", "2024-01-01T11:00:00Z"),
+      (
+        "TZ=\"Europe/Berlin\" 2024-07-01 12:00",
+        "2024-07-01T10:00:00Z",
+      ),
+      ("TZ=\"UTC-3\" 2024-07-01 12:00", "2024-07-01T09:00:00Z"),
+    ]);
+  }
+
+  #[test]
+  fn rejected_inputs() {
+    for bad in [
+      "bogus",
+      "13pm",
+      "0am",
+      "25:00",
+      "12:60",
+      "2024-13-01",
+      "2024-02-30",
+      "2024-05-01 2024-05-02",
+      "12:00 13:00",
+      "monday tuesday",
+      "UTC EST",
+      "@1 day",
+      "@",
+      "1.5 days",
+      "2024-01-01 12:00 +2500",
+      "TZ=\"Nowhere/Special\" 12:00",
+      "(unclosed",
+      "9223372036854775807 days",
+      "9223372036854775807 friday",
+      "-9223372036854775808 friday",
+    ] {
+      assert!(parse_date(bad, &now()).is_err(), "{:?} was accepted", bad);
+    }
+  }
+
+  #[test]
+  fn skipped_local_times_are_errors() {
+    let zone: chrono_tz::Tz = "America/New_York".parse().unwrap();
+    let now = now().with_timezone(&zone);
+    let error = parse_date("2024-03-10 02:30", &now).unwrap_err();
+    assert_eq!(
+      error.to_string(),
+      "invalid date format '2024-03-10 02:30': 2024-03-10 02:30:00 does not \
+       exist in this time zone"
+    );
+    // In the repeated hour the offset in effect at `now` is preferred.
+    let repeated = parse_date("2024-11-03 01:30", &now).unwrap();
+    assert_eq!(repeated.to_rfc3339(), "2024-11-03T01:30:00-04:00");
+  }
+}
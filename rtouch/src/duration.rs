@@ -0,0 +1,111 @@
+//! # Durations
+//!
+//! Parse compact signed durations such as `+2h`, `-1d30m`, `90s` or `1.5h`.
+// Imports. -------------------------------------------------------------------
+use chrono::TimeDelta;
+use std::io::{Error, ErrorKind};
+
+// Units. ---------------------------------------------------------------------
+
+/// The units, by every name they may be written with, in nanoseconds.
+const UNITS: [(&[&str], u128); 8] = [
+  (&["ns", "nsec"], 1),
+  (&["us", "usec"], 1_000),
+  (&["ms", "msec"], 1_000_000),
+  (&["s", "sec", "secs", "second", "seconds"], 1_000_000_000),
+  (&["m", "min", "mins", "minute", "minutes"], 60_000_000_000),
+  (&["h", "hr", "hrs", "hour", "hours"], 3_600_000_000_000),
+  (&["d", "day", "days"], 86_400_000_000_000),
+  (&["w", "week", "weeks"], 604_800_000_000_000),
+];
+
+// Functions. -----------------------------------------------------------------
+
+/// ## Parse a duration.
+///
+/// A duration is an optional sign followed by one or more numbers, each with
+/// a unit, e.g. `1h30m`. A lone number without a unit is in seconds. Numbers
+/// may have a fraction, e.g. `1.5h`.
+///
+/// ### Arguments:
+/// * `input` - The duration to parse.
+///
+/// ### Returns:
+/// * `Result<TimeDelta, Error>` - The parsed duration, which may be negative.
+pub fn parse_duration(input: &str) -> Result<TimeDelta, Error> {
+  let invalid = || {
+    Error::new(
+      ErrorKind::InvalidInput,
+      format!("invalid duration '{}'", input),
+    )
+  };
+  let (negative, mut rest) = match input.strip_prefix('-') {
+    Some(rest) => (true, rest),
+    None => (false, input.strip_prefix('+').unwrap_or(input)),
+  };
+  if rest.is_empty() {
+    return Err(invalid());
+  }
+  let mut nanos: u128 = 0;
+  while !rest.is_empty() {
+    let number_end = rest
+      .find(|c: char| !c.is_ascii_digit() && c != '.')
+      .unwrap_or(rest.len());
+    let unit_end = rest[number_end..]
+      .find(|c: char| c.is_ascii_digit() || c == '.')
+      .map_or(rest.len(), |end| number_end + end);
+    let (number, unit) = (&rest[..number_end], &rest[number_end..unit_end]);
+    // Only a lone number may be written without a unit.
+    if unit.is_empty() && nanos != 0 {
+      return Err(invalid());
+    }
+    let scale = match unit {
+      "" => 1_000_000_000,
+      unit => UNITS
+        .iter()
+        .find(|(names, _)| names.contains(&unit))
+        .map(|(_, scale)| *scale)
+        .ok_or_else(invalid)?,
+    };
+    nanos = nanos
+      .checked_add(scaled(number, scale).ok_or_else(invalid)?)
+      .ok_or_else(invalid)?;
+    rest = &rest[unit_end..];
+  }
+  let seconds = i64::try_from(nanos / 1_000_000_000).map_err(|_| invalid())?;
+  let delta = TimeDelta::try_seconds(seconds)
+    .and_then(|delta| {
+      delta.checked_add(&TimeDelta::nanoseconds((nanos % 1_000_000_000) as i64))
+    })
+    .ok_or_else(invalid)?;
+  Ok(if negative { -delta } else { delta })
+}
+
+/// ## Multiply a decimal number by a scale.
+///
+/// ### Arguments:
+/// * `number` - The number, with an optional fraction, e.g. `1.5`.
+/// * `scale` - The number of nanoseconds in one unit.
+///
+/// ### Returns:
+/// * `Option<u128>` - The number of nanoseconds, or `None` if the number is
+///   not valid.
+fn scaled(number: &str, scale: u128) -> Option<u128> {
+  let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+  if whole.is_empty() && fraction.is_empty() || fraction.contains('.') {
+    return None;
+  }
+  let whole: u128 = if whole.is_empty() {
+    0
+  } else {
+    whole.parse().ok()?
+  };
+  let mut nanos = whole.checked_mul(scale)?;
+  // Digits beyond nanosecond precision are dropped.
+  let mut place = scale;
+  for digit in fraction.bytes() {
+    place /= 10;
+    nanos = nanos.checked_add((digit - b'0') as u128 * place)?;
+  }
+  Some(nanos)
+}
@@ -0,0 +1,180 @@
+//! # Errors
+//!
+//! The ways touching a file can fail. Each error names the file involved,
+//! and is shown the way GNU `touch` reports it, e.g. "cannot touch 'a':
+//! Permission denied".
+// Imports. -------------------------------------------------------------------
+use crate::quote::quote;
+use std::{
+  fmt, io,
+  path::{Path, PathBuf},
+};
+
+// Types. ---------------------------------------------------------------------
+
+/// An error touching a file.
+#[derive(Debug)]
+pub enum TouchError {
+  /// A file could not be created, or its times set after it was.
+  Touch { path: PathBuf, source: io::Error },
+  /// The times of a file that already exists could not be set.
+  SetTimes { path: PathBuf, source: io::Error },
+  /// A missing parent directory could not be created.
+  CreateDirectory { path: PathBuf, source: io::Error },
+  /// The times of a file, or of a reference, could not be read.
+  Attributes { path: PathBuf, source: io::Error },
+  /// The git history of a file could not be read.
+  GitHistory { path: PathBuf, source: io::Error },
+  /// The saved times of a file could not be restored.
+  Restore { path: PathBuf, source: io::Error },
+  /// A file's size changed since its times were saved, so they were not
+  /// restored.
+  SizeChanged {
+    path: PathBuf,
+    saved: u64,
+    size: u64,
+  },
+  /// Shifting the times of a file took them out of range.
+  OutOfRange { path: PathBuf },
+  /// A date or time stamp could not be parsed.
+  InvalidTime(String),
+}
+
+// Functions. -----------------------------------------------------------------
+
+impl TouchError {
+  /// ## The kind of the error.
+  ///
+  /// ### Returns:
+  /// * `io::ErrorKind` - The kind of the underlying I/O error,
+  ///   `InvalidData` for a file whose size changed, or `InvalidInput` for a
+  ///   time that is not valid.
+  pub fn kind(&self) -> io::ErrorKind {
+    match self {
+      TouchError::Touch { source, .. }
+      | TouchError::SetTimes { source, .. }
+      | TouchError::CreateDirectory { source, .. }
+      | TouchError::Attributes { source, .. }
+      | TouchError::GitHistory { source, .. }
+      | TouchError::Restore { source, .. } => source.kind(),
+      TouchError::SizeChanged { .. } => io::ErrorKind::InvalidData,
+      TouchError::OutOfRange { .. } | TouchError::InvalidTime(_) => {
+        io::ErrorKind::InvalidInput
+      }
+    }
+  }
+
+  /// ## The file the error is about.
+  ///
+  /// ### Returns:
+  /// * `Option<&Path>` - The file, or `None` for a time that is not valid.
+  pub fn path(&self) -> Option<&Path> {
+    match self {
+      TouchError::Touch { path, .. }
+      | TouchError::SetTimes { path, .. }
+      | TouchError::CreateDirectory { path, .. }
+      | TouchError::Attributes { path, .. }
+      | TouchError::GitHistory { path, .. }
+      | TouchError::Restore { path, .. }
+      | TouchError::SizeChanged { path, .. }
+      | TouchError::OutOfRange { path } => Some(path),
+      TouchError::InvalidTime(_) => None,
+    }
+  }
+}
+
+impl fmt::Display for TouchError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let (context, path, source) = match self {
+      TouchError::Touch { path, source } => ("cannot touch", path, source),
+      TouchError::SetTimes { path, source } => {
+        ("setting times of", path, source)
+      }
+      TouchError::CreateDirectory { path, source } => {
+        ("cannot create directory", path, source)
+      }
+      TouchError::Attributes { path, source } => {
+        ("failed to get attributes of", path, source)
+      }
+      TouchError::GitHistory { path, source } => {
+        ("cannot read git history of", path, source)
+      }
+      TouchError::Restore { path, source } => {
+        ("cannot restore times of", path, source)
+      }
+      TouchError::SizeChanged { path, saved, size } => {
+        return write!(
+          f,
+          "cannot restore times of {}: size changed from {} to {} bytes",
+          quote(path),
+          saved,
+          size
+        );
+      }
+      TouchError::OutOfRange { path } => {
+        return write!(
+          f,
+          "cannot shift times of {}: time out of range",
+          quote(path)
+        );
+      }
+      TouchError::InvalidTime(message) => return f.write_str(message),
+    };
+    write!(f, "{} {}: {}", context, quote(path), message(source))
+  }
+}
+
+impl std::error::Error for TouchError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      TouchError::Touch { source, .. }
+      | TouchError::SetTimes { source, .. }
+      | TouchError::CreateDirectory { source, .. }
+      | TouchError::Attributes { source, .. }
+      | TouchError::GitHistory { source, .. }
+      | TouchError::Restore { source, .. } => Some(source),
+      TouchError::SizeChanged { .. }
+      | TouchError::OutOfRange { .. }
+      | TouchError::InvalidTime(_) => None,
+    }
+  }
+}
+
+impl From<TouchError> for io::Error {
+  fn from(error: TouchError) -> Self {
+    io::Error::new(error.kind(), error.to_string())
+  }
+}
+
+/// ## Add context to an I/O error.
+///
+/// The context is put in front of the error's message, without the
+/// "(os error N)" suffix, e.g. "cannot touch 'a': Permission denied".
+///
+/// ### Arguments:
+/// * `error` - The error.
+/// * `context` - What was being done when the error happened.
+///
+/// ### Returns:
+/// * `io::Error` - An error of the same kind with the context added.
+pub fn with_context(error: io::Error, context: &str) -> io::Error {
+  io::Error::new(error.kind(), format!("{}: {}", context, message(&error)))
+}
+
+/// ## The message of an I/O error, without its "(os error N)" suffix.
+///
+/// ### Arguments:
+/// * `error` - The error.
+///
+/// ### Returns:
+/// * `String` - The message, e.g. "Permission denied".
+fn message(error: &io::Error) -> String {
+  let message = error.to_string();
+  if let Some(code) = error.raw_os_error() {
+    let suffix = format!(" (os error {})", code);
+    if let Some(stripped) = message.strip_suffix(&suffix) {
+      return stripped.to_string();
+    }
+  }
+  message
+}
@@ -0,0 +1,199 @@
+//! # Git history
+//!
+//! Find the time of the last commit that changed a file, for `--from-git`,
+//! by reading the enclosing repository directly.
+// Imports. -------------------------------------------------------------------
+use git2::{Oid, Repository};
+use std::{
+  collections::HashMap,
+  fs,
+  io::Error,
+  path::{Path, PathBuf},
+  time::{Duration, SystemTime},
+};
+
+// Types. ---------------------------------------------------------------------
+
+/// The commit times of files in every repository seen so far.
+#[derive(Default)]
+pub struct History {
+  repositories: Vec<RepositoryTimes>,
+}
+
+/// The commit times of files in one repository, read as they are needed.
+struct RepositoryTimes {
+  repository: Repository,
+  /// The canonical path of the work tree.
+  workdir: PathBuf,
+  /// The next commit to read, or `None` once the history has been read.
+  next: Option<Oid>,
+  /// The time of the newest commit that changed each path read so far,
+  /// relative to the work tree, in seconds since the epoch.
+  times: HashMap<PathBuf, i64>,
+}
+
+// Functions. -----------------------------------------------------------------
+
+impl History {
+  /// ## Find the time of the last commit that changed a file.
+  ///
+  /// The history is followed from `HEAD` through first parents, and read
+  /// only as far as needed, so that files changed recently are found
+  /// quickly. The time of a directory is that of the last commit that
+  /// changed anything in it.
+  ///
+  /// ### Arguments:
+  /// * `file` - The file to look up.
+  ///
+  /// ### Returns:
+  /// * `Result<Option<SystemTime>, Error>` - The commit time, or `None` if
+  ///   the file is not in a repository or was never committed.
+  pub fn commit_time(
+    &mut self,
+    file: &Path,
+  ) -> Result<Option<SystemTime>, Error> {
+    let Some(path) = canonical(file) else {
+      return Ok(None);
+    };
+    let index = match self
+      .repositories
+      .iter()
+      .position(|times| path.starts_with(&times.workdir))
+    {
+      Some(index) => index,
+      None => match RepositoryTimes::discover(&path) {
+        Some(times) => {
+          self.repositories.push(times);
+          self.repositories.len() - 1
+        }
+        None => return Ok(None),
+      },
+    };
+    let times = &mut self.repositories[index];
+    let relative = path
+      .strip_prefix(&times.workdir)
+      .unwrap_or(&path)
+      .to_path_buf();
+    times.lookup(&relative).map_err(Error::other)
+  }
+}
+
+impl RepositoryTimes {
+  /// ## Open the repository a path is in.
+  ///
+  /// ### Arguments:
+  /// * `path` - The canonical path of a file.
+  ///
+  /// ### Returns:
+  /// * `Option<RepositoryTimes>` - The repository, or `None` if the path is
+  ///   not in a work tree.
+  fn discover(path: &Path) -> Option<RepositoryTimes> {
+    let repository = Repository::discover(path).ok()?;
+    let workdir = fs::canonicalize(repository.workdir()?).ok()?;
+    // A repository without any commits has no times to offer.
+    let next = repository.head().ok().and_then(|head| head.target());
+    Some(RepositoryTimes {
+      repository,
+      workdir,
+      next,
+      times: HashMap::new(),
+    })
+  }
+
+  /// ## Look up the commit time of a path.
+  ///
+  /// ### Arguments:
+  /// * `path` - The path, relative to the work tree.
+  ///
+  /// ### Returns:
+  /// * `Result<Option<SystemTime>, git2::Error>` - The commit time, if the
+  ///   path was ever committed.
+  fn lookup(&mut self, path: &Path) -> Result<Option<SystemTime>, git2::Error> {
+    loop {
+      if let Some(seconds) = self.times.get(path) {
+        return Ok(Some(system_time(*seconds)));
+      }
+      if self.next.is_none() {
+        return Ok(None);
+      }
+      self.read_next()?;
+    }
+  }
+
+  /// ## Read the next commit in the history.
+  ///
+  /// Every path the commit changed, and every directory above it, is given
+  /// the commit's time unless a newer commit already changed it.
+  ///
+  /// ### Returns:
+  /// * `Result<(), git2::Error>` - The result of the operation.
+  fn read_next(&mut self) -> Result<(), git2::Error> {
+    let Some(oid) = self.next else {
+      return Ok(());
+    };
+    let commit = self.repository.find_commit(oid)?;
+    let parent = commit.parent(0).ok();
+    let parent_tree =
+      parent.as_ref().map(|parent| parent.tree()).transpose()?;
+    let diff = self.repository.diff_tree_to_tree(
+      parent_tree.as_ref(),
+      Some(&commit.tree()?),
+      None,
+    )?;
+    let seconds = commit.committer().when().seconds();
+    for delta in diff.deltas() {
+      let files = [delta.old_file().path(), delta.new_file().path()];
+      for file in files.into_iter().flatten() {
+        for path in file.ancestors() {
+          // The directories above a path were recorded along with it.
+          if self.times.contains_key(path) {
+            break;
+          }
+          self.times.insert(path.to_path_buf(), seconds);
+        }
+      }
+    }
+    self.next = parent.map(|parent| parent.id());
+    Ok(())
+  }
+}
+
+/// ## Find the canonical path of a file.
+///
+/// The final component is not resolved, so that a symbolic link that is
+/// tracked is looked up as itself.
+///
+/// ### Arguments:
+/// * `file` - The file.
+///
+/// ### Returns:
+/// * `Option<PathBuf>` - The canonical path, if the file's directory exists.
+fn canonical(file: &Path) -> Option<PathBuf> {
+  match (file.parent(), file.file_name()) {
+    (Some(parent), Some(name)) => {
+      let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+      } else {
+        parent
+      };
+      Some(fs::canonicalize(parent).ok()?.join(name))
+    }
+    _ => fs::canonicalize(file).ok(),
+  }
+}
+
+/// ## Convert a commit time into a system time.
+///
+/// ### Arguments:
+/// * `seconds` - The seconds since the epoch, which may be negative.
+///
+/// ### Returns:
+/// * `SystemTime` - The time.
+fn system_time(seconds: i64) -> SystemTime {
+  let offset = Duration::from_secs(seconds.unsigned_abs());
+  if seconds < 0 {
+    SystemTime::UNIX_EPOCH - offset
+  } else {
+    SystemTime::UNIX_EPOCH + offset
+  }
+}
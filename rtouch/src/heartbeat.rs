@@ -0,0 +1,139 @@
+//! # Heartbeats
+//!
+//! Pace the rounds of `--every`, which touch the same files again and again
+//! until rtouch is stopped, its time is up or the process it watches exits.
+// Imports. -------------------------------------------------------------------
+use std::{
+  io::{Error, ErrorKind},
+  sync::atomic::{AtomicBool, Ordering},
+  thread,
+  time::{Duration, Instant},
+};
+
+// Types. ---------------------------------------------------------------------
+
+/// When to touch the files again, and when to stop.
+pub struct Heartbeat {
+  /// The time between the start of one round and the next.
+  every: Duration,
+  /// When the current round started.
+  last: Instant,
+  /// When to stop, with `--for`.
+  until: Option<Instant>,
+  /// The process to outlive, with `--pid`.
+  pid: Option<i32>,
+}
+
+/// Set by `SIGTERM` or `SIGINT` to stop after the current round.
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+/// How long to sleep at most before checking for a signal again.
+const POLL: Duration = Duration::from_millis(100);
+
+// Functions. -----------------------------------------------------------------
+
+impl Heartbeat {
+  /// ## Start beating, with the first round happening now.
+  ///
+  /// `SIGTERM` and `SIGINT` are caught from here on, so that they end the
+  /// program between rounds rather than in the middle of one.
+  ///
+  /// ### Arguments:
+  /// * `every` - The time between rounds.
+  /// * `duration` - How long to keep going, if not until stopped.
+  /// * `pid` - A process to stop with, if any.
+  ///
+  /// ### Returns:
+  /// * `Heartbeat` - The heartbeat.
+  pub fn start(
+    every: Duration,
+    duration: Option<Duration>,
+    pid: Option<i32>,
+  ) -> Heartbeat {
+    let handler = stop as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: `stop` only stores to an atomic, which is async-signal-safe.
+    unsafe {
+      libc::signal(libc::SIGTERM, handler);
+      libc::signal(libc::SIGINT, handler);
+    }
+    let last = Instant::now();
+    Heartbeat {
+      every,
+      last,
+      until: duration.map(|duration| last + duration),
+      pid,
+    }
+  }
+
+  /// ## Wait for the next round.
+  ///
+  /// Rounds keep to their schedule however long each takes, unless one
+  /// overruns the interval, in which case the next starts straight away.
+  ///
+  /// ### Returns:
+  /// * `bool` - Whether to touch the files again, or stop.
+  pub fn wait(&mut self) -> bool {
+    let next = (self.last + self.every).max(Instant::now());
+    loop {
+      if STOPPED.load(Ordering::SeqCst) || !self.watched_process_alive() {
+        return false;
+      }
+      let now = Instant::now();
+      if self.until.is_some_and(|until| now >= until) {
+        return false;
+      }
+      if now >= next {
+        self.last = next;
+        return true;
+      }
+      let mut wake = next.min(now + POLL);
+      if let Some(until) = self.until {
+        wake = wake.min(until);
+      }
+      thread::sleep(wake - now);
+    }
+  }
+
+  /// ## Check whether the process given with `--pid` is still running.
+  ///
+  /// A process that exists but belongs to another user still counts, and
+  /// without `--pid` there is nothing to outlive.
+  ///
+  /// ### Returns:
+  /// * `bool` - Whether to keep going.
+  fn watched_process_alive(&self) -> bool {
+    let Some(pid) = self.pid else {
+      return true;
+    };
+    // SAFETY: signal 0 only checks that the process can be signalled.
+    if unsafe { libc::kill(pid, 0) } == 0 {
+      return true;
+    }
+    Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+  }
+}
+
+/// ## Parse the interval of `--every` or the duration of `--for`.
+///
+/// ### Arguments:
+/// * `input` - The duration to parse, as for `--shift`.
+///
+/// ### Returns:
+/// * `Result<Duration, Error>` - The duration, which must be positive.
+pub fn parse_interval(input: &str) -> Result<Duration, Error> {
+  rtouch::parse_duration(input)?
+    .to_std()
+    .ok()
+    .filter(|interval| !interval.is_zero())
+    .ok_or_else(|| {
+      Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid interval '{}'", input),
+      )
+    })
+}
+
+/// ## Record a request to stop, from a signal handler.
+extern "C" fn stop(_: libc::c_int) {
+  STOPPED.store(true, Ordering::SeqCst);
+}
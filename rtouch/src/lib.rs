@@ -0,0 +1,974 @@
+//! # rtouch
+//!
+//! Update the access and modification times of files, creating them if they
+//! do not exist, as `touch` does. The `rtouch` command is a thin layer over
+//! this library, so everything it can do to a file can be done from Rust:
+//!
+//! ```no_run
+//! use rtouch::{touch, Action, TouchOptions};
+//! use std::time::SystemTime;
+//!
+//! let time = rtouch::parse_time("2024-05-01 12:00:00", SystemTime::now())?;
+//! let options = TouchOptions::new().time(time).no_create(true);
+//! let outcome = touch("Cargo.toml", &options)?;
+//! assert_eq!(outcome.action, Action::Updated);
+//! # Ok::<(), rtouch::TouchError>(())
+//! ```
+// Modules. -------------------------------------------------------------------
+mod date;
+mod duration;
+mod error;
+mod git;
+mod mode;
+mod quote;
+mod time;
+mod walk;
+
+// Imports. -------------------------------------------------------------------
+use chrono::{DateTime, TimeDelta, Utc};
+use git::History;
+use std::{
+  ffi::CString,
+  fs::{self, DirBuilder, FileTimes, Metadata, OpenOptions, Permissions},
+  io::{Error, ErrorKind},
+  os::unix::{
+    ffi::OsStrExt,
+    fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt},
+  },
+  path::{Path, PathBuf},
+  sync::{Arc, Mutex, PoisonError},
+  time::SystemTime,
+};
+
+// Exports. -------------------------------------------------------------------
+pub use duration::parse_duration;
+pub use error::{with_context, TouchError};
+pub use mode::{parse_mode, Mode};
+pub use quote::quote;
+pub use time::{parse_stamp, parse_time};
+pub use walk::Walk;
+
+// Types. ---------------------------------------------------------------------
+
+/// The access and modification times to set. A time that is `None` is left
+/// untouched.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timestamps {
+  pub accessed: Option<SystemTime>,
+  pub modified: Option<SystemTime>,
+  /// Whether the times are the current time, as when none is given. They are
+  /// then set as `UTIME_NOW`, as GNU does, which the kernel also allows on a
+  /// file that can be written but is owned by someone else.
+  pub now: bool,
+}
+
+impl From<Timestamps> for FileTimes {
+  fn from(times: Timestamps) -> Self {
+    let mut file_times = FileTimes::new();
+    if let Some(accessed) = times.accessed {
+      file_times = file_times.set_accessed(accessed);
+    }
+    if let Some(modified) = times.modified {
+      file_times = file_times.set_modified(modified);
+    }
+    file_times
+  }
+}
+
+/// What was done to a file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+  Created,
+  Updated,
+  Skipped,
+}
+
+/// What `touch` did to a file, or would have done with `dry_run`.
+#[derive(Clone, Debug)]
+pub struct TouchOutcome {
+  /// What was done to the file.
+  pub action: Action,
+  /// The file's times beforehand, where they are known. They are only read
+  /// with `old_times`.
+  pub old: Timestamps,
+  /// The times that were set. A file that was created has both times set,
+  /// even if only one was asked for, and times set to the current time are
+  /// those the file ended up with, unless they were only worked out.
+  pub set: Timestamps,
+  /// The missing parent directories that were created with `parents`, from
+  /// the top down.
+  pub directories: Vec<PathBuf>,
+}
+
+/// The times of a file saved to be restored later, with its size to tell
+/// whether it has changed since.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SavedTimes {
+  pub accessed: SystemTime,
+  pub modified: SystemTime,
+  pub size: u64,
+}
+
+impl From<SavedTimes> for Timestamps {
+  fn from(saved: SavedTimes) -> Self {
+    Timestamps {
+      accessed: Some(saved.accessed),
+      modified: Some(saved.modified),
+      now: false,
+    }
+  }
+}
+
+/// How `touch` updates a file.
+///
+/// The options start out as plain `touch` with no arguments: both times are
+/// set to the time the options were made, and a missing file is created.
+/// Each method changes one option, in the manner of a builder, e.g.
+/// `TouchOptions::new().time(time).no_create(true)`.
+#[derive(Clone)]
+pub struct TouchOptions {
+  times: Timestamps,
+  no_create: bool,
+  no_dereference: bool,
+  parents: bool,
+  exclusive: bool,
+  mode: Option<Mode>,
+  dir_mode: Option<Mode>,
+  clamp: bool,
+  shift: Option<TimeDelta>,
+  if_older_than: Option<SystemTime>,
+  if_newer_than: Option<SystemTime>,
+  /// The git history read so far, shared between copies of the options,
+  /// with `from_git`.
+  history: Option<Arc<Mutex<History>>>,
+  dry_run: bool,
+  old_times: bool,
+}
+
+// Functions. -----------------------------------------------------------------
+
+impl Default for TouchOptions {
+  fn default() -> Self {
+    TouchOptions::new()
+  }
+}
+
+impl TouchOptions {
+  /// ## Make the options of plain `touch`.
+  ///
+  /// ### Returns:
+  /// * `TouchOptions` - Options that set both times to now.
+  pub fn new() -> TouchOptions {
+    let now = SystemTime::now();
+    TouchOptions {
+      times: Timestamps {
+        accessed: Some(now),
+        modified: Some(now),
+        now: true,
+      },
+      no_create: false,
+      no_dereference: false,
+      parents: false,
+      exclusive: false,
+      mode: None,
+      dir_mode: None,
+      clamp: false,
+      shift: None,
+      if_older_than: None,
+      if_newer_than: None,
+      history: None,
+      dry_run: false,
+      old_times: false,
+    }
+  }
+
+  /// ## Set both times to `time`.
+  pub fn time(self, time: SystemTime) -> TouchOptions {
+    self.times(Timestamps {
+      accessed: Some(time),
+      modified: Some(time),
+      now: false,
+    })
+  }
+
+  /// ## Set the times to `times`.
+  ///
+  /// A time that is `None` is left untouched, as `-a` and `-m` do.
+  pub fn times(mut self, times: Timestamps) -> TouchOptions {
+    self.times = times;
+    self
+  }
+
+  /// ## Leave missing files alone, like `-c`.
+  pub fn no_create(mut self, no_create: bool) -> TouchOptions {
+    self.no_create = no_create;
+    self
+  }
+
+  /// ## Affect symbolic links rather than their targets, like `-h`.
+  ///
+  /// Missing files are then never created.
+  pub fn no_dereference(mut self, no_dereference: bool) -> TouchOptions {
+    self.no_dereference = no_dereference;
+    self
+  }
+
+  /// ## Create any missing parent directories, like `-p`.
+  pub fn parents(mut self, parents: bool) -> TouchOptions {
+    self.parents = parents;
+    self
+  }
+
+  /// ## Create each file, failing if it exists, like `--exclusive`.
+  pub fn exclusive(mut self, exclusive: bool) -> TouchOptions {
+    self.exclusive = exclusive;
+    self
+  }
+
+  /// ## Give files that are created exactly `mode`, like `--mode`.
+  pub fn mode(mut self, mode: Mode) -> TouchOptions {
+    self.mode = Some(mode);
+    self
+  }
+
+  /// ## Give directories created exactly `mode`, like `--dir-mode`.
+  pub fn dir_mode(mut self, mode: Mode) -> TouchOptions {
+    self.dir_mode = Some(mode);
+    self
+  }
+
+  /// ## Only lower times that are newer, like `--clamp`.
+  pub fn clamp(mut self, clamp: bool) -> TouchOptions {
+    self.clamp = clamp;
+    self
+  }
+
+  /// ## Shift each file's own times by `shift`, like `--shift`.
+  ///
+  /// Only the times that are being set are shifted.
+  pub fn shift(mut self, shift: TimeDelta) -> TouchOptions {
+    self.shift = Some(shift);
+    self
+  }
+
+  /// ## Only touch files modified before `time`, like `--if-older-than`.
+  ///
+  /// Other files, including missing ones, are skipped.
+  pub fn if_older_than(mut self, time: SystemTime) -> TouchOptions {
+    self.if_older_than = Some(time);
+    self
+  }
+
+  /// ## Only touch files modified after `time`, like `--if-newer-than`.
+  ///
+  /// Other files, including missing ones, are skipped.
+  pub fn if_newer_than(mut self, time: SystemTime) -> TouchOptions {
+    self.if_newer_than = Some(time);
+    self
+  }
+
+  /// ## Take modification times from git history, like `--from-git`.
+  ///
+  /// Each file gets the time of the last commit to change it, if there is
+  /// one. The history is read once and shared between clones of the
+  /// options.
+  pub fn from_git(mut self, from_git: bool) -> TouchOptions {
+    self.history = from_git.then(Arc::default);
+    self
+  }
+
+  /// ## Work out what would be done, without changing anything, like `-n`.
+  pub fn dry_run(mut self, dry_run: bool) -> TouchOptions {
+    self.dry_run = dry_run;
+    self
+  }
+
+  /// ## Read each file's times beforehand, for the outcome's `old`.
+  pub fn old_times(mut self, old_times: bool) -> TouchOptions {
+    self.old_times = old_times;
+    self
+  }
+}
+
+/// ## Touch a single file.
+///
+/// ### Arguments:
+/// * `path` - The file to update.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<TouchOutcome, TouchError>` - What was done to the file.
+pub fn touch<P: AsRef<Path>>(
+  path: P,
+  options: &TouchOptions,
+) -> Result<TouchOutcome, TouchError> {
+  let file = path.as_ref();
+  let old = match options.old_times {
+    true => metadata(file, !options.no_dereference)
+      .map(|metadata| Timestamps {
+        accessed: metadata.accessed().ok(),
+        modified: metadata.modified().ok(),
+        now: false,
+      })
+      .unwrap_or_default(),
+    false => Timestamps::default(),
+  };
+  let mut directories = Vec::new();
+  let (action, set) = match file_times(file, options)? {
+    Some(times) => {
+      let action = update_file(file, times, options, &mut directories)?;
+      (action, applied_times(file, times, action, options))
+    }
+    None => (Action::Skipped, Timestamps::default()),
+  };
+  Ok(TouchOutcome {
+    action,
+    old,
+    set,
+    directories,
+  })
+}
+
+/// ## Read the times of a file, such as a reference for `-r`.
+///
+/// ### Arguments:
+/// * `path` - The file.
+/// * `follow` - Whether to follow a final symbolic link.
+///
+/// ### Returns:
+/// * `Result<(SystemTime, SystemTime), TouchError>` - The access and
+///   modification times of the file.
+pub fn read_times<P: AsRef<Path>>(
+  path: P,
+  follow: bool,
+) -> Result<(SystemTime, SystemTime), TouchError> {
+  let path = path.as_ref();
+  metadata(path, follow)
+    .and_then(|metadata| Ok((metadata.accessed()?, metadata.modified()?)))
+    .map_err(|source| TouchError::Attributes {
+      path: path.to_path_buf(),
+      source,
+    })
+}
+
+/// ## Save the times of a file, to restore them later.
+///
+/// ### Arguments:
+/// * `path` - The file.
+/// * `follow` - Whether to follow a final symbolic link.
+///
+/// ### Returns:
+/// * `Result<SavedTimes, TouchError>` - The times and size of the file.
+pub fn save_times<P: AsRef<Path>>(
+  path: P,
+  follow: bool,
+) -> Result<SavedTimes, TouchError> {
+  let path = path.as_ref();
+  metadata(path, follow)
+    .and_then(|metadata| {
+      Ok(SavedTimes {
+        accessed: metadata.accessed()?,
+        modified: metadata.modified()?,
+        size: metadata.len(),
+      })
+    })
+    .map_err(|source| TouchError::Attributes {
+      path: path.to_path_buf(),
+      source,
+    })
+}
+
+/// ## Restore the saved times of a file.
+///
+/// Only the times that `options` set are restored, so that `-a` or `-m`
+/// restores just one. A file that has vanished is never created, and one
+/// whose size has changed since its times were saved is left alone, as its
+/// contents have likely changed too. The size of a directory is not checked,
+/// as it changes with the entries in it.
+///
+/// ### Arguments:
+/// * `path` - The file.
+/// * `saved` - The times saved by `save_times`.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<TouchOutcome, TouchError>` - What was done to the file.
+pub fn restore_times<P: AsRef<Path>>(
+  path: P,
+  saved: &SavedTimes,
+  options: &TouchOptions,
+) -> Result<TouchOutcome, TouchError> {
+  let file = path.as_ref();
+  let metadata = metadata(file, !options.no_dereference).map_err(|source| {
+    TouchError::Restore {
+      path: file.to_path_buf(),
+      source,
+    }
+  })?;
+  if !metadata.is_dir() && metadata.len() != saved.size {
+    return Err(TouchError::SizeChanged {
+      path: file.to_path_buf(),
+      saved: saved.size,
+      size: metadata.len(),
+    });
+  }
+  let times = Timestamps {
+    accessed: options.times.accessed.and(Some(saved.accessed)),
+    modified: options.times.modified.and(Some(saved.modified)),
+    now: false,
+  };
+  touch(file, &options.clone().times(times).no_create(true))
+}
+
+/// ## Work out the times to set on a file.
+///
+/// Most times are the same for every file, but with `shift` they are the
+/// file's own times moved by the duration, and with `from_git` the time of
+/// the last commit to change the file, if there is one. With `clamp` only
+/// the times that are newer than those to be set are changed. With
+/// `if_older_than` or `if_newer_than` a file whose modification time does not
+/// meet the condition is left alone.
+///
+/// ### Arguments:
+/// * `file` - The file to update.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<Option<Timestamps>, TouchError>` - The times to set, or `None`
+///   if the file is to be left alone.
+fn file_times(
+  file: &Path,
+  options: &TouchOptions,
+) -> Result<Option<Timestamps>, TouchError> {
+  if !conditions_hold(file, options)? {
+    return Ok(None);
+  }
+  let mut times = options.times;
+  if let Some(history) = &options.history {
+    let commit_time = history
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+      .commit_time(file)
+      .map_err(|source| TouchError::GitHistory {
+        path: file.to_path_buf(),
+        source,
+      })?;
+    if commit_time.is_some() {
+      times.modified = commit_time;
+      times.now = false;
+    }
+  }
+  if options.clamp {
+    return clamp_times(file, times, options);
+  }
+  let Some(shift) = options.shift else {
+    return Ok(Some(times));
+  };
+  // A missing file has no times to shift, so it is never created.
+  let (accessed, modified) = match read_times(file, !options.no_dereference) {
+    Err(error) if error.kind() == ErrorKind::NotFound && options.no_create => {
+      return Ok(None);
+    }
+    result => result?,
+  };
+  let shifted = |time: SystemTime| {
+    shift_time(time, shift).ok_or_else(|| TouchError::OutOfRange {
+      path: file.to_path_buf(),
+    })
+  };
+  Ok(Some(Timestamps {
+    accessed: times.accessed.map(|_| shifted(accessed)).transpose()?,
+    modified: times.modified.map(|_| shifted(modified)).transpose()?,
+    now: false,
+  }))
+}
+
+/// ## Work out the times a file really has after it was touched.
+///
+/// With only one of the times set on a file that was created, as with `-a`
+/// or `-m`, the other is the time the file was created, and times set to the
+/// current time are whatever the kernel made of it. Both are read back from
+/// the file.
+///
+/// ### Arguments:
+/// * `file` - The file that was touched.
+/// * `times` - The times that were set on it.
+/// * `action` - What was done to it.
+/// * `options` - How it was touched.
+///
+/// ### Returns:
+/// * `Timestamps` - The times that were set, where they could be read.
+fn applied_times(
+  file: &Path,
+  times: Timestamps,
+  action: Action,
+  options: &TouchOptions,
+) -> Timestamps {
+  let created = action == Action::Created;
+  let incomplete = times.accessed.is_none() || times.modified.is_none();
+  if options.dry_run
+    || action == Action::Skipped
+    || !(times.now || created && incomplete)
+  {
+    return times;
+  }
+  let Ok(metadata) = metadata(file, !options.no_dereference) else {
+    return times;
+  };
+  let read =
+    |time: Option<SystemTime>, actual: Result<SystemTime, Error>| match time {
+      Some(_) if times.now => actual.ok().or(time),
+      None if created => actual.ok(),
+      time => time,
+    };
+  Timestamps {
+    accessed: read(times.accessed, metadata.accessed()),
+    modified: read(times.modified, metadata.modified()),
+    now: times.now,
+  }
+}
+
+/// ## Check a file's modification time against `if_older_than` and
+/// `if_newer_than`.
+///
+/// The times are compared strictly, and a missing file meets no condition.
+///
+/// ### Arguments:
+/// * `file` - The file to check.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<bool, TouchError>` - Whether the file meets every condition.
+fn conditions_hold(
+  file: &Path,
+  options: &TouchOptions,
+) -> Result<bool, TouchError> {
+  if options.if_older_than.is_none() && options.if_newer_than.is_none() {
+    return Ok(true);
+  }
+  let modified = match read_times(file, !options.no_dereference) {
+    Ok((_, modified)) => modified,
+    Err(error) if error.kind() == ErrorKind::NotFound => return Ok(false),
+    Err(error) => return Err(error),
+  };
+  Ok(
+    options.if_older_than.is_none_or(|limit| modified < limit)
+      && options.if_newer_than.is_none_or(|limit| modified > limit),
+  )
+}
+
+/// ## Keep only the times that lower a file's own.
+///
+/// A missing file has no times of its own, so it is created as usual.
+///
+/// ### Arguments:
+/// * `file` - The file to update.
+/// * `times` - The times to set at most.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<Option<Timestamps>, TouchError>` - The times to set, or `None`
+///   if none of them are newer.
+fn clamp_times(
+  file: &Path,
+  times: Timestamps,
+  options: &TouchOptions,
+) -> Result<Option<Timestamps>, TouchError> {
+  let metadata = match metadata(file, !options.no_dereference) {
+    Err(error) if error.kind() == ErrorKind::NotFound => {
+      return Ok(Some(times));
+    }
+    result => result.map_err(|source| TouchError::Attributes {
+      path: file.to_path_buf(),
+      source,
+    })?,
+  };
+  let lower = |limit: Option<SystemTime>,
+               current: Result<SystemTime, Error>| {
+    limit.filter(|limit| current.is_ok_and(|current| current > *limit))
+  };
+  let times = Timestamps {
+    accessed: lower(times.accessed, metadata.accessed()),
+    modified: lower(times.modified, metadata.modified()),
+    now: false,
+  };
+  if times.accessed.is_none() && times.modified.is_none() {
+    return Ok(None);
+  }
+  Ok(Some(times))
+}
+
+/// ## Shift a time by a duration.
+///
+/// A time outside the range of a date is out of range too, as it could not be
+/// reported, and the file system would silently clamp it.
+///
+/// ### Arguments:
+/// * `time` - The time to shift.
+/// * `shift` - The duration, which may be negative.
+///
+/// ### Returns:
+/// * `Option<SystemTime>` - The shifted time, or `None` if it is out of range.
+fn shift_time(time: SystemTime, shift: TimeDelta) -> Option<SystemTime> {
+  let shifted = match shift.to_std() {
+    Ok(forward) => time.checked_add(forward),
+    Err(_) => time.checked_sub((-shift).to_std().ok()?),
+  }?;
+  let range = SystemTime::from(DateTime::<Utc>::MIN_UTC)
+    ..=SystemTime::from(DateTime::<Utc>::MAX_UTC);
+  range.contains(&shifted).then_some(shifted)
+}
+
+/// ## Read the metadata of a path.
+///
+/// ### Arguments:
+/// * `path` - The path to read.
+/// * `follow` - Whether to follow a final symbolic link.
+///
+/// ### Returns:
+/// * `Result<Metadata, Error>` - The metadata of the path, or of a symbolic
+///   link itself.
+fn metadata(path: &Path, follow: bool) -> Result<Metadata, Error> {
+  if follow {
+    fs::metadata(path)
+  } else {
+    fs::symlink_metadata(path)
+  }
+}
+
+/// ## Update the access and modification times of a file.
+///
+/// The times are set by path, so directories and read-only files can be
+/// updated by their owner. The file is only opened when it has to be created,
+/// which never happens with `no_create` or `no_dereference`. With `exclusive`
+/// it is always created, and a file that already exists is an error.
+///
+/// ### Arguments:
+/// * `file` - The file to update.
+/// * `time` - The time to update the file to.
+/// * `options` - How to update it.
+/// * `directories` - Where to record the parent directories created.
+///
+/// ### Returns:
+/// * `Result<Action, TouchError>` - What was done to the file.
+fn update_file(
+  file: &Path,
+  time: Timestamps,
+  options: &TouchOptions,
+  directories: &mut Vec<PathBuf>,
+) -> Result<Action, TouchError> {
+  if options.exclusive {
+    return create_missing(file, time, options, directories);
+  }
+  let result = match set_file_times(file, time, options) {
+    Err(error) if error.kind() == ErrorKind::NotFound => {
+      if options.no_create {
+        return Ok(Action::Skipped);
+      }
+      if !options.no_dereference {
+        return create_missing(file, time, options, directories);
+      }
+      Err(error)
+    }
+    result => result.map(|_| Action::Updated),
+  };
+  // As with GNU, a file that would have been created "cannot be touched",
+  // while other failures are in "setting times".
+  result.map_err(|source| {
+    if options.no_create || options.no_dereference {
+      TouchError::SetTimes {
+        path: file.to_path_buf(),
+        source,
+      }
+    } else {
+      TouchError::Touch {
+        path: file.to_path_buf(),
+        source,
+      }
+    }
+  })
+}
+
+/// ## Create a missing file, and with `parents` its missing parents.
+///
+/// The parents are only given `dir_mode` once the file has been created, from
+/// the bottom up, so that a mode which does not let the user write to or
+/// search a directory does not stop anything being created inside it.
+///
+/// ### Arguments:
+/// * `file` - The file to create.
+/// * `time` - The times to set.
+/// * `options` - How to create it.
+/// * `directories` - Where to record the parent directories created.
+///
+/// ### Returns:
+/// * `Result<Action, TouchError>` - Whether the file was created or, having
+///   appeared in the meantime, updated.
+fn create_missing(
+  file: &Path,
+  time: Timestamps,
+  options: &TouchOptions,
+  directories: &mut Vec<PathBuf>,
+) -> Result<Action, TouchError> {
+  if options.parents {
+    create_parents(file, options, directories)?;
+  }
+  let created =
+    create_file(file, time, options).map_err(|source| TouchError::Touch {
+      path: file.to_path_buf(),
+      source,
+    });
+  // The directories get their mode even if the file could not be created.
+  let moded = set_dir_modes(directories, options);
+  let action = created?;
+  moded.map(|_| action)
+}
+
+/// ## Create the missing parent directories of a file.
+///
+/// The directories are created from the top down with the default mode
+/// filtered by the umask. With `dir_mode` they are created with only that
+/// mode, plus write and search for the user, and only the directories that
+/// were created are recorded, to be given `dir_mode` later. A name with a
+/// trailing slash cannot be created as a file, so no parents are created for
+/// it either.
+///
+/// ### Arguments:
+/// * `file` - The file whose parents are created.
+/// * `options` - How to update the file.
+/// * `directories` - Where to record the directories created.
+///
+/// ### Returns:
+/// * `Result<(), TouchError>` - The result of the operation.
+fn create_parents(
+  file: &Path,
+  options: &TouchOptions,
+  directories: &mut Vec<PathBuf>,
+) -> Result<(), TouchError> {
+  let Some(parent) = file.parent() else {
+    return Ok(());
+  };
+  if file.as_os_str().as_bytes().ends_with(b"/") {
+    return Ok(());
+  }
+  let missing: Vec<&Path> = parent
+    .ancestors()
+    .take_while(|dir| {
+      !dir.as_os_str().is_empty() && fs::symlink_metadata(dir).is_err()
+    })
+    .collect();
+  let mode = match &options.dir_mode {
+    Some(mode) => mode.bits(true) & 0o777 | 0o300,
+    None => 0o777,
+  };
+  for dir in missing.into_iter().rev() {
+    if !options.dry_run {
+      match DirBuilder::new().mode(mode).create(dir) {
+        // Another process may have created it in the meantime.
+        Err(error)
+          if error.kind() == ErrorKind::AlreadyExists && dir.is_dir() =>
+        {
+          continue;
+        }
+        result => result.map_err(|source| TouchError::CreateDirectory {
+          path: dir.to_path_buf(),
+          source,
+        })?,
+      }
+    }
+    directories.push(dir.to_path_buf());
+  }
+  Ok(())
+}
+
+/// ## Give the parent directories that were created `dir_mode`.
+///
+/// The deepest directory is done first, so that the others can still be
+/// searched while it is.
+///
+/// ### Arguments:
+/// * `directories` - The directories that were created, from the top down.
+/// * `options` - How they were created.
+///
+/// ### Returns:
+/// * `Result<(), TouchError>` - The result of the operation.
+fn set_dir_modes(
+  directories: &[PathBuf],
+  options: &TouchOptions,
+) -> Result<(), TouchError> {
+  let Some(mode) = &options.dir_mode else {
+    return Ok(());
+  };
+  if options.dry_run {
+    return Ok(());
+  }
+  let mode = mode.bits(true);
+  for dir in directories.iter().rev() {
+    fs::set_permissions(dir, Permissions::from_mode(mode)).map_err(
+      |source| TouchError::CreateDirectory {
+        path: dir.to_path_buf(),
+        source,
+      },
+    )?;
+  }
+  Ok(())
+}
+
+/// ## Create a missing file and set its times.
+///
+/// With `exclusive` the file is created with `O_EXCL`, so that it fails if
+/// the file, or a symbolic link, already exists. Otherwise a dangling link
+/// creates its target. As another process may have created the file in the
+/// meantime, it is only given `mode` if it is still empty and belongs to the
+/// user; any other file just has its times set.
+///
+/// ### Arguments:
+/// * `file` - The file to create.
+/// * `time` - The times to set.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<Action, Error>` - Whether the file was created or, having
+///   appeared in the meantime, updated.
+fn create_file(
+  file: &Path,
+  time: Timestamps,
+  options: &TouchOptions,
+) -> Result<Action, Error> {
+  if options.dry_run {
+    return check_create(file, options).map(|_| Action::Created);
+  }
+  let mode = options.mode.as_ref().map(|mode| mode.bits(false));
+  // A file created since the times were set must not be truncated.
+  let created = OpenOptions::new()
+    .write(true)
+    .create(true)
+    .create_new(options.exclusive)
+    .truncate(false)
+    .mode(mode.unwrap_or(0o666))
+    .open(file)?;
+  if let Some(mode) = mode {
+    let metadata = created.metadata()?;
+    // SAFETY: `geteuid` has no preconditions and cannot fail.
+    let ours = metadata.uid() == unsafe { libc::geteuid() };
+    if metadata.len() != 0 || !ours {
+      return set_file_times(file, time, options).map(|_| Action::Updated);
+    }
+    created.set_permissions(Permissions::from_mode(mode))?;
+  }
+  created.set_times(time.into()).map(|_| Action::Created)
+}
+
+/// ## Check that a file could be created, for `dry_run`.
+///
+/// ### Arguments:
+/// * `file` - The file that would be created.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<(), Error>` - The error creating the file would fail with, if
+///   it is known in advance.
+fn check_create(file: &Path, options: &TouchOptions) -> Result<(), Error> {
+  if options.exclusive && fs::symlink_metadata(file).is_ok() {
+    return Err(Error::from_raw_os_error(libc::EEXIST));
+  }
+  // With `parents` the missing parents would have been created first, but
+  // not for a name with a trailing slash, which cannot be created at all.
+  let slash = file.as_os_str().as_bytes().ends_with(b"/");
+  let parent = match file.parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent,
+    _ => Path::new("."),
+  };
+  match fs::metadata(parent) {
+    Ok(metadata) if !metadata.is_dir() => {
+      Err(Error::from_raw_os_error(libc::ENOTDIR))
+    }
+    Err(error) if !options.parents || slash => Err(error),
+    _ if slash => Err(Error::from_raw_os_error(libc::EISDIR)),
+    _ => Ok(()),
+  }
+}
+
+/// ## Set the times of a file, or check that it exists with `dry_run`.
+///
+/// ### Arguments:
+/// * `file` - The file to update.
+/// * `time` - The times to set.
+/// * `options` - How to update it.
+///
+/// ### Returns:
+/// * `Result<(), Error>` - The result of the operation.
+fn set_file_times(
+  file: &Path,
+  time: Timestamps,
+  options: &TouchOptions,
+) -> Result<(), Error> {
+  if options.dry_run {
+    return metadata(file, !options.no_dereference).map(|_| ());
+  }
+  set_times(file, time, !options.no_dereference)
+}
+
+/// ## Set the times of a path.
+///
+/// ### Arguments:
+/// * `path` - The path to update.
+/// * `time` - The times to set.
+/// * `follow` - Whether to follow a final symbolic link.
+///
+/// ### Returns:
+/// * `Result<(), Error>` - The result of the operation.
+fn set_times(path: &Path, time: Timestamps, follow: bool) -> Result<(), Error> {
+  let path = CString::new(path.as_os_str().as_bytes())?;
+  let times = [
+    timespec(time.accessed, time.now),
+    timespec(time.modified, time.now),
+  ];
+  let flags = if follow { 0 } else { libc::AT_SYMLINK_NOFOLLOW };
+  // SAFETY: `path` is NUL terminated and `times` holds two timespecs.
+  let result = unsafe {
+    libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), flags)
+  };
+  if result == -1 {
+    return Err(Error::last_os_error());
+  }
+  Ok(())
+}
+
+/// ## Convert a time into a timespec for `utimensat`.
+///
+/// ### Arguments:
+/// * `time` - The time, or `None` to leave the time untouched.
+/// * `now` - Whether the time is the current time.
+///
+/// ### Returns:
+/// * `libc::timespec` - The timespec, which may be before the epoch.
+fn timespec(time: Option<SystemTime>, now: bool) -> libc::timespec {
+  let Some(time) = time else {
+    return libc::timespec {
+      tv_sec: 0,
+      tv_nsec: libc::UTIME_OMIT,
+    };
+  };
+  if now {
+    return libc::timespec {
+      tv_sec: 0,
+      tv_nsec: libc::UTIME_NOW,
+    };
+  }
+  let (seconds, nanos) = match time.duration_since(SystemTime::UNIX_EPOCH) {
+    Ok(since) => (since.as_secs() as i64, since.subsec_nanos() as i64),
+    Err(error) => {
+      // Before the epoch the nanoseconds still count forward from a second.
+      let before = error.duration();
+      match before.subsec_nanos() {
+        0 => (-(before.as_secs() as i64), 0),
+        nanos => (-(before.as_secs() as i64) - 1, 1_000_000_000 - nanos as i64),
+      }
+    }
+  };
+  libc::timespec {
+    tv_sec: seconds as libc::time_t,
+    tv_nsec: nanos as libc::c_long,
+  }
+}
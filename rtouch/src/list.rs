@@ -0,0 +1,90 @@
+//! # File lists
+//!
+//! Read the files to update from a list, for `--files-from` and
+//! `--files0-from`, so that there can be more than fit on a command line.
+// Imports. -------------------------------------------------------------------
+use rtouch::{quote, with_context};
+use std::{
+  ffi::OsString,
+  fs::File,
+  io::{self, BufRead, BufReader, Error, ErrorKind},
+  os::unix::ffi::{OsStrExt, OsStringExt},
+  path::{Path, PathBuf},
+};
+
+// Types. ---------------------------------------------------------------------
+
+/// A list of file names, read one at a time.
+pub struct FileList {
+  /// The name of the list, where "-" is standard input.
+  name: PathBuf,
+  /// Where the list is read from.
+  reader: Box<dyn BufRead>,
+  /// The byte that ends each file name, a newline or NUL.
+  separator: u8,
+  /// The number of file names read so far.
+  count: usize,
+}
+
+// Functions. -----------------------------------------------------------------
+
+impl FileList {
+  /// ## Open a list of file names.
+  ///
+  /// ### Arguments:
+  /// * `name` - The file to read, or "-" for standard input.
+  /// * `separator` - The byte that ends each file name, a newline or NUL.
+  ///
+  /// ### Returns:
+  /// * `Result<FileList, Error>` - The list, ready to be read.
+  pub fn open(name: &Path, separator: u8) -> Result<FileList, Error> {
+    let reader: Box<dyn BufRead> = match name.as_os_str().as_bytes() {
+      b"-" => Box::new(io::stdin().lock()),
+      _ => Box::new(BufReader::new(File::open(name)?)),
+    };
+    Ok(FileList {
+      name: name.to_path_buf(),
+      reader,
+      separator,
+      count: 0,
+    })
+  }
+}
+
+impl Iterator for FileList {
+  type Item = Result<PathBuf, Error>;
+
+  /// ## Read the next file name.
+  ///
+  /// A name may hold any byte but the separator, so NUL separated lists can
+  /// name files with newlines in them. An empty name is an error, as it
+  /// cannot name a file. Reading stops after an error reading the list.
+  fn next(&mut self) -> Option<Self::Item> {
+    let mut name = Vec::new();
+    match self.reader.read_until(self.separator, &mut name) {
+      Ok(0) => return None,
+      Ok(_) => {}
+      Err(error) => {
+        self.reader = Box::new(io::empty());
+        let context =
+          format!("cannot read file names from {}", quote(&self.name));
+        return Some(Err(with_context(error, &context)));
+      }
+    }
+    self.count += 1;
+    if name.last() == Some(&self.separator) {
+      name.pop();
+    }
+    if name.is_empty() {
+      return Some(Err(Error::new(
+        ErrorKind::InvalidInput,
+        format!(
+          "{}:{}: invalid zero-length file name",
+          quote(&self.name),
+          self.count
+        ),
+      )));
+    }
+    Some(Ok(PathBuf::from(OsString::from_vec(name))))
+  }
+}
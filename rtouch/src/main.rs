@@ -1,18 +1,39 @@
 /// # rtouch
 ///
 /// Update the access and modification times of each FILE to the current time.
+/// The work is done by the `rtouch` library, and this maps the command line
+/// onto it.
+// Modules. -------------------------------------------------------------------
+mod heartbeat;
+mod list;
+mod manifest;
+mod report;
+
 // Imports. -------------------------------------------------------------------
-use chrono::DateTime;
-use clap::Parser;
+use chrono::TimeDelta;
+use clap::{ArgAction, CommandFactory, Parser, ValueEnum};
+use glob::Pattern;
+use heartbeat::Heartbeat;
+use list::FileList;
+use manifest::{Entry, ManifestReader, ManifestWriter};
+use report::Report;
+use rtouch::{quote, with_context, Mode, Timestamps, TouchOptions, Walk};
 use std::{
-  fs::{File, FileTimes, OpenOptions},
+  env,
   io::{Error, ErrorKind},
+  path::{Path, PathBuf},
+  process::ExitCode,
   time::{Duration, SystemTime},
 };
 
+// Constants. -----------------------------------------------------------------
+
+/// The name diagnostics are prefixed with.
+const PROGRAM: &str = env!("CARGO_PKG_NAME");
+
 // Argument parsing. ----------------------------------------------------------
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, disable_help_flag = true)]
 /// Update the access and modification times of each FILE to the current time.
 struct Args {
   /// Change the access time only.
@@ -23,190 +44,588 @@ struct Args {
   #[arg(short('c'), long("no-create"), default_value = "false")]
   no_create: bool,
 
+  /// Only lower times that are newer than the time being set.
+  #[arg(long("clamp"), default_value = "false", conflicts_with = "shift")]
+  clamp: bool,
+
+  /// Create each file, failing if it already exists.
+  #[arg(long("exclusive"), default_value = "false", conflicts_with_all = ["no_create", "shift"])]
+  exclusive: bool,
+
+  /// Parse a free-form date string, e.g. "yesterday 14:00" or "2 hours ago".
+  #[arg(short('d'), long("date"), default_value = None, allow_hyphen_values = true, conflicts_with = "time")]
+  date: Option<String>,
+
+  /// Affect each symbolic link instead of any referenced file.
+  #[arg(short('h'), long("no-dereference"), default_value = "false")]
+  no_dereference: bool,
+
   /// Change the modification time only.
   #[arg(short('m'), long = None, conflicts_with = "update_access_only", default_value = "false")]
   update_modification_only: bool,
 
+  /// Create files with this octal or symbolic MODE, e.g. 0600 or u=rw.
+  #[arg(long("mode"), value_name = "MODE", value_parser = rtouch::parse_mode)]
+  mode: Option<Mode>,
+
+  /// Create any missing parent directories.
+  #[arg(short('p'), long("parents"), default_value = "false")]
+  parents: bool,
+
+  /// With -p, create directories with this octal or symbolic MODE.
+  #[arg(long("dir-mode"), value_name = "MODE", value_parser = rtouch::parse_mode, requires = "parents")]
+  dir_mode: Option<Mode>,
+
   /// Use this file's times instead of the current time.
   #[arg(short('r'), long("reference"), conflicts_with = "time", default_value = None)]
-  reference_file: Option<String>,
+  reference_file: Option<PathBuf>,
+
+  /// Update directories and their contents recursively.
+  #[arg(short('R'), long("recursive"), default_value = "false")]
+  recursive: bool,
+
+  /// With -R, only update files matching GLOB, by name or relative path.
+  #[arg(long("include"), value_name = "GLOB", value_parser = Pattern::new, requires = "recursive")]
+  include: Vec<Pattern>,
+
+  /// With -R, skip entries matching GLOB, and everything below them.
+  #[arg(long("exclude"), value_name = "GLOB", value_parser = Pattern::new, requires = "recursive")]
+  exclude: Vec<Pattern>,
+
+  /// With -R, descend at most N levels below each FILE.
+  #[arg(long("max-depth"), value_name = "N", requires = "recursive")]
+  max_depth: Option<usize>,
+
+  /// With -R, follow symbolic links to directories.
+  #[arg(
+    long("follow-symlinks"),
+    default_value = "false",
+    requires = "recursive"
+  )]
+  follow_symlinks: bool,
+
+  /// With -R, leave the times of directories themselves alone.
+  #[arg(long("no-dirs"), default_value = "false", requires = "recursive")]
+  no_dirs: bool,
+
+  /// Shift each file's own times by DURATION, e.g. "+2h" or "-1d30m".
+  #[arg(long("shift"), value_name = "DURATION", allow_hyphen_values = true, value_parser = rtouch::parse_duration, conflicts_with_all = ["date", "reference_file", "time"])]
+  shift: Option<TimeDelta>,
+
+  /// Set modification times to those of the last git commit to change each file.
+  #[arg(long("from-git"), default_value = "false", conflicts_with_all = ["update_access_only", "shift"])]
+  from_git: bool,
 
-  /// Attempt to parse the time string.
-  #[arg(short('t'), long("time"), default_value = None, conflicts_with = "reference_file")]
+  /// Only update files last modified before REF's modification time or TIME.
+  #[arg(long("if-older-than"), value_name = "REF|TIME", allow_hyphen_values = true, conflicts_with_all = ["exclusive", "save_times"])]
+  if_older_than: Option<PathBuf>,
+
+  /// Only update files last modified after REF's modification time or TIME.
+  #[arg(long("if-newer-than"), value_name = "REF|TIME", allow_hyphen_values = true, conflicts_with_all = ["exclusive", "save_times"])]
+  if_newer_than: Option<PathBuf>,
+
+  /// Use [[CC]YY]MMDDhhmm[.ss] instead of the current time.
+  #[arg(short('t'), value_name = "STAMP", default_value = None, conflicts_with_all = ["date", "reference_file"])]
   time: Option<String>,
 
+  /// Change only this time, like -a or -m; atime and use mean access, mtime means modify.
+  #[arg(long("time"), value_name = "WORD", value_enum, default_value = None)]
+  time_word: Option<TimeWord>,
+
+  /// Report what is done to each file and directory.
+  #[arg(short('v'), long("verbose"), default_value = "false")]
+  verbose: bool,
+
+  /// Work out what would be done, without changing anything.
+  #[arg(short('n'), long("dry-run"), default_value = "false")]
+  dry_run: bool,
+
+  /// Report what is done to each file as a line of JSON.
+  #[arg(long("json"), default_value = "false", conflicts_with = "verbose")]
+  json: bool,
+
+  /// Keep touching each FILE with the current time every DURATION until stopped.
+  #[arg(long("every"), value_name = "DURATION", value_parser = heartbeat::parse_interval, conflicts_with_all = ["date", "reference_file", "time", "shift", "from_git", "clamp", "exclusive", "dry_run", "files_from", "files0_from", "save_times", "restore_times"])]
+  every: Option<Duration>,
+
+  /// With --every, stop after DURATION.
+  #[arg(long("for"), value_name = "DURATION", value_parser = heartbeat::parse_interval, requires = "every")]
+  duration: Option<Duration>,
+
+  /// With --every, stop once the process with this PID has exited.
+  #[arg(long("pid"), value_name = "PID", value_parser = clap::value_parser!(i32).range(1..), requires = "every")]
+  pid: Option<i32>,
+
+  /// Save the times of each FILE to MANIFEST instead of changing them.
+  #[arg(long("save-times"), value_name = "MANIFEST", conflicts_with_all = ["restore_times", "date", "reference_file", "time", "shift", "from_git", "clamp", "exclusive", "dry_run"])]
+  save_times: Option<PathBuf>,
+
+  /// Put back the times saved in MANIFEST by --save-times.
+  #[arg(long("restore-times"), value_name = "MANIFEST", conflicts_with_all = ["FILE", "files_from", "files0_from", "date", "reference_file", "time", "shift", "from_git", "clamp", "exclusive"])]
+  restore_times: Option<PathBuf>,
+
+  /// Read the files to update from FILE, one per line, or "-" for stdin.
+  #[arg(long("files-from"), value_name = "FILE", conflicts_with_all = ["FILE", "files0_from"])]
+  files_from: Option<PathBuf>,
+
+  /// Read the files to update from FILE, separated by NUL characters.
+  #[arg(long("files0-from"), value_name = "FILE", conflicts_with = "FILE")]
+  files0_from: Option<PathBuf>,
+
   /// Files to update.
-  #[arg(name = "FILE", required = true)]
-  files: Vec<String>,
+  #[arg(name = "FILE", required_unless_present_any = ["files_from", "files0_from", "restore_times"])]
+  files: Vec<PathBuf>,
+
+  /// Print help.
+  #[arg(long, action = ArgAction::Help)]
+  help: Option<bool>,
+}
+
+// Types. --------------------------------------------------------------------
+
+/// The time changed by `--time`. The variants are left undocumented so that
+/// the help keeps its short layout.
+#[derive(Clone, Copy, ValueEnum)]
+enum TimeWord {
+  #[value(aliases = ["atime", "use"])]
+  Access,
+  #[value(aliases = ["mtime"])]
+  Modify,
 }
 
 // Main entry point. ----------------------------------------------------------
-fn main() -> Result<(), Error> {
+fn main() -> ExitCode {
   let time = SystemTime::now();
-  let args = Args::parse();
-  let mut file_times = FileTimes::new();
-  // The default is to update both the access and modification times.
-  file_times = file_times.set_accessed(time).set_modified(time);
+  let mut args = Args::parse();
+  apply_time_word(&mut args);
+  // Only the modification time is kept in git's history.
+  args.update_modification_only |= args.from_git;
 
-  // If a time is provided, use it instead of the current time.
-  if let Some(time) = &args.time {
-    match parse_time(time) {
-      Ok(system_time) => {
-        if args.update_access_only {
-          file_times = file_times.set_accessed(system_time);
-        } else if args.update_modification_only {
-          file_times = file_times.set_modified(system_time);
-        } else {
-          file_times = file_times
-            .set_accessed(system_time)
-            .set_modified(system_time);
+  // The times are worked out before any file is touched, so that a bad time
+  // or reference leaves every file alone.
+  let options = match resolve_times(time, &args)
+    .and_then(|times| touch_options(time, times, &args))
+  {
+    Ok(options) => options,
+    Err(error) => {
+      eprintln!("{}: {}", PROGRAM, error);
+      return ExitCode::FAILURE;
+    }
+  };
+
+  // Update the access and modification times of each file.
+  let mut status = ExitCode::SUCCESS;
+  let mut report = |file: &Path, result: Result<Report, Error>| {
+    if args.json {
+      println!("{}", report::json(file, &result, args.dry_run));
+    }
+    match result {
+      Ok(done) if args.verbose => {
+        if let Report::Touched(outcome) = &done {
+          for dir in &outcome.directories {
+            println!("{}: {}", PROGRAM, report::directory(dir, args.dry_run));
+          }
         }
+        println!(
+          "{}: {}",
+          PROGRAM,
+          report::verbose(file, &done, args.dry_run)
+        )
       }
+      Ok(_) => {}
       Err(error) => {
-        eprintln!("Error parsing time: {}", error);
+        eprintln!("{}: {}", PROGRAM, error);
+        status = ExitCode::FAILURE;
       }
     }
-  }
-  // If a file reference is provided, use its times instead of the current time.
-  else if let Some(reference) = &args.reference_file {
-    match parse_reference(reference, &args) {
-      Ok(times) => {
-        file_times = times;
-      }
+  };
+  if let Some(name) = &args.restore_times {
+    let manifest = match ManifestReader::open(name) {
+      Ok(manifest) => manifest,
       Err(error) => {
-        let error_type = "Error parsing reference file:";
-        match error.kind() {
-          ErrorKind::NotFound => {
-            eprintln!("{} File not found", error_type);
-          }
-          ErrorKind::PermissionDenied => {
-            eprintln!("{} Permission denied", error_type);
-          }
-          ErrorKind::Unsupported => {
-            eprintln!("{} Unsupported operation", error_type);
-          }
-          _ => {
-            eprintln!("{} {}", error_type, error);
-          }
-        }
+        let context = format!("cannot open {} for reading", quote(name));
+        eprintln!("{}: {}", PROGRAM, with_context(error, &context));
+        return ExitCode::FAILURE;
+      }
+    };
+    for entry in manifest {
+      match entry {
+        Ok(entry) => report(
+          &entry.path,
+          rtouch::restore_times(&entry.path, &entry.times, &options)
+            .map(Report::Touched)
+            .map_err(Error::from),
+        ),
+        Err(error) => report(name, Err(error)),
       }
     }
+    return status;
   }
-
-  // Update the access and modification times of each file.
-  for file in &args.files {
-    match update_file(file, file_times, &args) {
-      Ok(_) => {}
+  let walk = Walk {
+    include: &args.include,
+    exclude: &args.exclude,
+    max_depth: args.max_depth,
+    follow_links: args.follow_symlinks,
+    directories: !args.no_dirs,
+  };
+  // The files come from the command line, or one at a time from a list.
+  let list = match (&args.files_from, &args.files0_from) {
+    (Some(name), _) => Some((name, b'\n')),
+    (_, Some(name)) => Some((name, b'\0')),
+    _ => None,
+  };
+  let mut operands: Box<dyn Iterator<Item = Result<PathBuf, Error>>> =
+    match list {
+      Some((name, separator)) => match FileList::open(name, separator) {
+        Ok(list) => Box::new(list),
+        Err(error) => {
+          let context = format!("cannot open {} for reading", quote(name));
+          eprintln!("{}: {}", PROGRAM, with_context(error, &context));
+          return ExitCode::FAILURE;
+        }
+      },
+      None => Box::new(args.files.iter().cloned().map(Ok)),
+    };
+  let mut manifest = match &args.save_times {
+    Some(name) => match ManifestWriter::create(name) {
+      Ok(manifest) => Some(manifest),
       Err(error) => {
-        let error_type = "Error updating file:";
-        match error.kind() {
-          ErrorKind::PermissionDenied => {
-            eprintln!("{} Permission denied", error_type);
-          }
-          ErrorKind::Unsupported => {
-            eprintln!("{} Unsupported operation", error_type);
+        let context = format!("cannot create {}", quote(name));
+        eprintln!("{}: {}", PROGRAM, with_context(error, &context));
+        return ExitCode::FAILURE;
+      }
+    },
+    None => None,
+  };
+  let mut visit = |file: &Path, options: &TouchOptions| match &mut manifest {
+    Some(manifest) => save(file, manifest, &args),
+    None => rtouch::touch(file, options)
+      .map(Report::Touched)
+      .map_err(Error::from),
+  };
+  let mut touch_all =
+    |operands: &mut dyn Iterator<Item = Result<PathBuf, Error>>,
+     options: &TouchOptions| {
+      for operand in operands {
+        let file = match operand {
+          Ok(file) => file,
+          Err(error) => {
+            let name = list
+              .map(|(name, _)| name.as_path())
+              .unwrap_or(Path::new(""));
+            report(name, Err(error));
+            continue;
           }
-          _ => {
-            eprintln!("{} {}", error_type, error);
+        };
+        if !args.recursive || !file.is_dir() {
+          report(&file, visit(&file, options));
+          continue;
+        }
+        for entry in walk.walk(&file) {
+          match entry {
+            Ok(path) => report(&path, visit(&path, options)),
+            Err(error) => {
+              let path = error.path().unwrap_or(&file).to_path_buf();
+              report(&path, Err(walk_error(error)));
+            }
           }
         }
       }
+    };
+  // With --every the files are touched again until it is time to stop, each
+  // round with the time at which it starts.
+  let mut heartbeat = args
+    .every
+    .map(|every| Heartbeat::start(every, args.duration, args.pid));
+  touch_all(&mut operands, &options);
+  while heartbeat.as_mut().is_some_and(Heartbeat::wait) {
+    let time = SystemTime::now();
+    let options = options.clone().times(current_times(time, &args));
+    touch_all(&mut args.files.iter().cloned().map(Ok), &options);
+  }
+  if let (Some(manifest), Some(name)) = (manifest, &args.save_times) {
+    if let Err(error) = manifest.finish() {
+      report(name, Err(error));
     }
   }
-  Ok(())
+  status
 }
 
 // Functions. -----------------------------------------------------------------
 
-/// ## Parse the time string.
+/// ## Apply `--time` as the equivalent `-a` or `-m`.
+///
+/// The word is subject to the same conflicts as the option it stands for, and
+/// the program exits with a usage error if one is found.
 ///
 /// ### Arguments:
-/// * `time` - The time string to parse.
+/// * `args` - The command line arguments.
+fn apply_time_word(args: &mut Args) {
+  let conflict = match args.time_word {
+    Some(TimeWord::Access) if args.update_modification_only => "-m",
+    Some(TimeWord::Modify) if args.update_access_only => "-a",
+    Some(TimeWord::Access) if args.from_git => "--from-git",
+    Some(TimeWord::Access) => {
+      args.update_access_only = true;
+      return;
+    }
+    Some(TimeWord::Modify) => {
+      args.update_modification_only = true;
+      return;
+    }
+    None => return,
+  };
+  Args::command()
+    .error(
+      clap::error::ErrorKind::ArgumentConflict,
+      format!(
+        "the argument '--time <WORD>' cannot be used with '{}'",
+        conflict
+      ),
+    )
+    .exit()
+}
+
+/// ## Resolve the times to set from the command line arguments.
+///
+/// Without `-t`, `-r` or `-d` the time is taken from `SOURCE_DATE_EPOCH` if
+/// it is set, for reproducible builds, and is otherwise the current time.
+/// A heartbeat with `--every` always uses the current time, and
+/// `--shift`, `--save-times` and `--restore-times` never use it.
+///
+/// ### Arguments:
+/// * `time` - The current time.
+/// * `args` - The command line arguments.
 ///
 /// ### Returns:
-/// * `Result<SystemTime, Error>` - The parsed time.
-fn parse_time(time: &str) -> Result<SystemTime, Error> {
-  let formats = [
-    // ISO 8601
-    "%Y-%m-%dT%H:%M:%S.%3f%z",
-    "%Y-%m-%dT%H:%M:%S%z",
-    // With SPACE as time separator
-    "%Y-%m-%d %H:%M:%S.%3f%z",
-    "%Y-%m-%d %H:%M:%S%z",
-    // Without time separator
-    "%Y-%m-%d%H:%M:%S.%3f%z",
-    "%Y-%m-%d%H:%M:%S%z",
-  ];
-  for format in formats {
-    match DateTime::parse_from_str(time, format) {
-      Ok(offset) => {
-        if let Some(date_time) = DateTime::from_timestamp(0, 0) {
-          return Ok(
-            SystemTime::UNIX_EPOCH
-              + Duration::from_secs(
-                offset.signed_duration_since(date_time).num_seconds() as u64,
-              ),
-          );
-        }
-      }
-      Err(_) => continue,
+/// * `Result<Timestamps, Error>` - The times to set on each file.
+fn resolve_times(time: SystemTime, args: &Args) -> Result<Timestamps, Error> {
+  // If a time is provided, use it instead of the current time.
+  if let Some(stamp) = &args.time {
+    let time = rtouch::parse_stamp(stamp, time)?;
+    return Ok(select_times(time, time, args));
+  }
+  // If a file reference is provided, use its times instead.
+  let (accessed, modified) = match &args.reference_file {
+    Some(reference) => rtouch::read_times(reference, !args.no_dereference)?,
+    None => (time, time),
+  };
+  // The variable is only read when it would be the time that is set, so that
+  // a bad value does not stop the options that keep each file's own times.
+  if args.reference_file.is_none()
+    && args.date.is_none()
+    && args.every.is_none()
+    && args.shift.is_none()
+    && args.save_times.is_none()
+    && args.restore_times.is_none()
+  {
+    if let Some(epoch) = source_date_epoch()? {
+      return Ok(select_times(epoch, epoch, args));
     }
   }
-  Err(Error::new(ErrorKind::InvalidInput, "Unsupported date format"))
+  // A date is resolved against each of the times separately, so that with a
+  // reference "-1 hour" moves both of its times back by an hour.
+  if let Some(date) = &args.date {
+    let accessed = rtouch::parse_time(date, accessed)?;
+    let modified = rtouch::parse_time(date, modified)?;
+    return Ok(select_times(accessed, modified, args));
+  }
+  match args.reference_file {
+    Some(_) => Ok(select_times(accessed, modified, args)),
+    None => Ok(current_times(time, args)),
+  }
 }
 
-/// ## Parse the reference file.
+/// ## Read the time set by `SOURCE_DATE_EPOCH`.
+///
+/// An empty value is treated as if it were not set, and any other value
+/// that is not a whole number of seconds since the epoch is an error.
+///
+/// ### Returns:
+/// * `Result<Option<SystemTime>, Error>` - The time, if one is set.
+fn source_date_epoch() -> Result<Option<SystemTime>, Error> {
+  let Some(epoch) = env::var_os("SOURCE_DATE_EPOCH") else {
+    return Ok(None);
+  };
+  if epoch.is_empty() {
+    return Ok(None);
+  }
+  epoch
+    .to_str()
+    .filter(|epoch| epoch.bytes().all(|b| b.is_ascii_digit()))
+    .and_then(|epoch| epoch.parse().ok())
+    .and_then(|seconds| {
+      SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(seconds))
+    })
+    .map(Some)
+    .ok_or_else(|| {
+      Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid SOURCE_DATE_EPOCH {}", quote(&epoch)),
+      )
+    })
+}
+
+/// ## Map the command line arguments onto the options of the library.
 ///
 /// ### Arguments:
-/// * `path` - The path to the reference file.
+/// * `time` - The current time.
+/// * `times` - The times resolved from the command line.
 /// * `args` - The command line arguments.
 ///
 /// ### Returns:
-/// * `Result<FileTimes, Error>` - The file times of the reference file.
-fn parse_reference(path: &str, args: &Args) -> Result<FileTimes, Error> {
-  let file_times = FileTimes::new();
-  let metadata = File::open(path)?.metadata()?;
-
-  if args.update_access_only {
-    Ok(file_times.set_accessed(metadata.accessed()?))
-  } else if args.update_modification_only {
-    Ok(file_times.set_modified(metadata.accessed()?))
-  } else {
-    Ok(
-      file_times
-        .set_accessed(metadata.accessed()?)
-        .set_modified(metadata.modified()?),
-    )
+/// * `Result<TouchOptions, Error>` - The options to touch each file with.
+fn touch_options(
+  time: SystemTime,
+  times: Timestamps,
+  args: &Args,
+) -> Result<TouchOptions, Error> {
+  let mut options = TouchOptions::new()
+    .times(times)
+    .no_create(args.no_create)
+    .no_dereference(args.no_dereference)
+    .parents(args.parents)
+    .exclusive(args.exclusive)
+    .clamp(args.clamp)
+    .from_git(args.from_git)
+    .dry_run(args.dry_run)
+    // The old times are only needed for the JSON report.
+    .old_times(args.json);
+  if let Some(mode) = &args.mode {
+    options = options.mode(mode.clone());
+  }
+  if let Some(mode) = &args.dir_mode {
+    options = options.dir_mode(mode.clone());
+  }
+  if let Some(shift) = args.shift {
+    options = options.shift(shift);
+  }
+  if let Some(limit) = &args.if_older_than {
+    options = options.if_older_than(resolve_limit(limit, time, args)?);
   }
+  if let Some(limit) = &args.if_newer_than {
+    options = options.if_newer_than(resolve_limit(limit, time, args)?);
+  }
+  Ok(options)
 }
 
-/// ## Update the access and modification times of a file.
+/// ## Resolve the time given to `--if-older-than` or `--if-newer-than`.
+///
+/// A value naming an existing file stands for that file's modification
+/// time, whatever its name, and any other value is parsed as a date, like
+/// `-d`.
 ///
 /// ### Arguments:
-/// * `file` - The file to update.
-/// * `time` - The time to update the file to.
+/// * `limit` - The reference file or date.
+/// * `time` - The current time.
 /// * `args` - The command line arguments.
 ///
 /// ### Returns:
-/// * `Result<(), Error>` - The result of the operation.
-fn update_file(file: &str, time: FileTimes, args: &Args) -> Result<(), Error> {
-  match OpenOptions::new().write(true).open(file) {
-    Ok(file) => {
-      file.set_times(time)?;
-    }
-    Err(error) => match error.kind() {
-      ErrorKind::NotFound => {
-        if !args.no_create {
-          match File::create(file) {
-            Ok(_) => update_file(file, time, args)?,
-            Err(error) => {
-              eprintln!("Error creating file: {}", error)
-            }
-          };
-        }
-      }
-      _ => return Err(error),
-    },
+/// * `Result<SystemTime, Error>` - The time to compare each file against.
+fn resolve_limit(
+  limit: &Path,
+  time: SystemTime,
+  args: &Args,
+) -> Result<SystemTime, Error> {
+  match rtouch::read_times(limit, !args.no_dereference) {
+    Ok((_, modified)) => Ok(modified),
+    Err(error) if error.kind() == ErrorKind::NotFound => limit
+      .to_str()
+      .and_then(|date| rtouch::parse_time(date, time).ok())
+      .ok_or_else(|| {
+        Error::new(
+          ErrorKind::InvalidInput,
+          format!("{} is neither a file nor a valid date", quote(limit)),
+        )
+      }),
+    Err(error) => Err(error.into()),
+  }
+}
+
+/// ## Save the times of a file to a manifest, for `--save-times`.
+///
+/// ### Arguments:
+/// * `file` - The file whose times are saved.
+/// * `manifest` - The manifest to add the file to.
+/// * `args` - The command line arguments.
+///
+/// ### Returns:
+/// * `Result<Report, Error>` - The times that were saved.
+fn save(
+  file: &Path,
+  manifest: &mut ManifestWriter,
+  args: &Args,
+) -> Result<Report, Error> {
+  let times = rtouch::save_times(file, !args.no_dereference)?;
+  manifest.write(&Entry {
+    path: file.to_path_buf(),
+    times,
+  })?;
+  Ok(Report::Saved(times.into()))
+}
+
+/// ## Select the times to update.
+///
+/// With `-a` or `-m` only that time is set, and the other is left untouched.
+///
+/// ### Arguments:
+/// * `accessed` - The access time to use.
+/// * `modified` - The modification time to use.
+/// * `args` - The command line arguments.
+///
+/// ### Returns:
+/// * `Timestamps` - The times to update.
+fn select_times(
+  accessed: SystemTime,
+  modified: SystemTime,
+  args: &Args,
+) -> Timestamps {
+  Timestamps {
+    accessed: Some(accessed).filter(|_| !args.update_modification_only),
+    modified: Some(modified).filter(|_| !args.update_access_only),
+    now: false,
+  }
+}
+
+/// ## Select the times to update to the current time.
+///
+/// The times are set as the current time rather than as `time`, so that
+/// files that can be written but belong to someone else can be touched.
+///
+/// ### Arguments:
+/// * `time` - The current time.
+/// * `args` - The command line arguments.
+///
+/// ### Returns:
+/// * `Timestamps` - The times to update.
+fn current_times(time: SystemTime, args: &Args) -> Timestamps {
+  Timestamps {
+    now: true,
+    ..select_times(time, time, args)
+  }
+}
+
+/// ## Turn an error from a recursive walk into a diagnostic.
+///
+/// ### Arguments:
+/// * `error` - The error.
+///
+/// ### Returns:
+/// * `Error` - The error, naming the path that could not be read.
+fn walk_error(error: walkdir::Error) -> Error {
+  let context = match error.path() {
+    Some(path) => format!("cannot access {}", quote(path)),
+    None => "cannot access".to_string(),
   };
-  Ok(())
+  if let Some(ancestor) = error.loop_ancestor() {
+    return Error::other(format!(
+      "{}: file system loop back to {}",
+      context,
+      quote(ancestor)
+    ));
+  }
+  match error.into_io_error() {
+    Some(error) => with_context(error, &context),
+    None => Error::other(context),
+  }
 }
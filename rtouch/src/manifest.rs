@@ -0,0 +1,414 @@
+//! # Manifests
+//!
+//! Save the times of files to a manifest with `--save-times`, and read them
+//! back with `--restore-times`.
+//!
+//! Each line of a manifest holds a file's access time, modification time,
+//! size and path, separated by tabs, e.g. `1700000000.123456789`,
+//! `1700000000.000000000`, `42` and `src/main.rs`.
+//!
+//! Times are seconds since the epoch with nanoseconds, and are negative
+//! before it. In paths, a backslash, tab, newline or carriage return is
+//! written as `\\`, `\t`, `\n` or `\r`, and any other control character or
+//! byte that is not valid UTF-8 as `\xHH`. Lines starting with `#` are
+//! comments.
+// Imports. -------------------------------------------------------------------
+use rtouch::{quote, with_context, SavedTimes};
+use std::{
+  ffi::OsString,
+  fs::File,
+  io::{self, BufRead, BufReader, BufWriter, Error, ErrorKind, Write},
+  os::unix::ffi::{OsStrExt, OsStringExt},
+  path::{Path, PathBuf},
+  time::{Duration, SystemTime},
+};
+
+// Constants. -----------------------------------------------------------------
+
+/// The first line of every manifest.
+const HEADER: &str = "# rtouch times: atime\tmtime\tsize\tpath";
+
+// Types. ---------------------------------------------------------------------
+
+/// The saved times of a file.
+pub struct Entry {
+  pub path: PathBuf,
+  pub times: SavedTimes,
+}
+
+/// A manifest being written.
+pub struct ManifestWriter {
+  name: PathBuf,
+  writer: BufWriter<File>,
+}
+
+/// A manifest being read, one entry at a time.
+pub struct ManifestReader {
+  name: PathBuf,
+  reader: Box<dyn BufRead>,
+  /// The number of lines read so far.
+  line: usize,
+}
+
+// Functions. -----------------------------------------------------------------
+
+impl ManifestWriter {
+  /// ## Create a manifest.
+  ///
+  /// ### Arguments:
+  /// * `name` - The file to write, which is replaced if it exists.
+  ///
+  /// ### Returns:
+  /// * `Result<ManifestWriter, Error>` - The manifest, ready for entries.
+  pub fn create(name: &Path) -> Result<ManifestWriter, Error> {
+    let mut manifest = ManifestWriter {
+      name: name.to_path_buf(),
+      writer: BufWriter::new(File::create(name)?),
+    };
+    writeln!(manifest.writer, "{}", HEADER)
+      .map_err(|error| manifest.error(error))?;
+    Ok(manifest)
+  }
+
+  /// ## Add an entry to the manifest.
+  ///
+  /// ### Arguments:
+  /// * `entry` - The entry to add.
+  ///
+  /// ### Returns:
+  /// * `Result<(), Error>` - The result of the operation.
+  pub fn write(&mut self, entry: &Entry) -> Result<(), Error> {
+    writeln!(
+      self.writer,
+      "{}\t{}\t{}\t{}",
+      format_time(entry.times.accessed),
+      format_time(entry.times.modified),
+      entry.times.size,
+      escape(entry.path.as_os_str().as_bytes())
+    )
+    .map_err(|error| self.error(error))
+  }
+
+  /// ## Finish writing the manifest.
+  ///
+  /// ### Returns:
+  /// * `Result<(), Error>` - The result of the operation.
+  pub fn finish(mut self) -> Result<(), Error> {
+    self.writer.flush().map_err(|error| self.error(error))
+  }
+
+  /// ## Add the manifest's name to an error writing it.
+  fn error(&self, error: Error) -> Error {
+    with_context(error, &format!("cannot write {}", quote(&self.name)))
+  }
+}
+
+impl ManifestReader {
+  /// ## Open a manifest.
+  ///
+  /// ### Arguments:
+  /// * `name` - The file to read.
+  ///
+  /// ### Returns:
+  /// * `Result<ManifestReader, Error>` - The manifest, ready to be read.
+  pub fn open(name: &Path) -> Result<ManifestReader, Error> {
+    Ok(ManifestReader {
+      name: name.to_path_buf(),
+      reader: Box::new(BufReader::new(File::open(name)?)),
+      line: 0,
+    })
+  }
+
+  /// ## Parse a line of the manifest.
+  ///
+  /// ### Arguments:
+  /// * `line` - The line, without its newline.
+  ///
+  /// ### Returns:
+  /// * `Option<Entry>` - The entry, or `None` if the line is not valid.
+  fn parse(line: &[u8]) -> Option<Entry> {
+    let mut fields = line.splitn(4, |b| *b == b'\t');
+    let mut text = || std::str::from_utf8(fields.next()?).ok();
+    let accessed = parse_time(text()?)?;
+    let modified = parse_time(text()?)?;
+    let size = text()?.parse().ok()?;
+    let path = PathBuf::from(OsString::from_vec(unescape(fields.next()?)?));
+    Some(Entry {
+      path,
+      times: SavedTimes {
+        accessed,
+        modified,
+        size,
+      },
+    })
+  }
+}
+
+impl Iterator for ManifestReader {
+  type Item = Result<Entry, Error>;
+
+  /// ## Read the next entry.
+  ///
+  /// Reading stops after an error reading the manifest, but carries on
+  /// after a line that is not valid.
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let mut line = Vec::new();
+      match self.reader.read_until(b'\n', &mut line) {
+        Ok(0) => return None,
+        Ok(_) => {}
+        Err(error) => {
+          self.reader = Box::new(io::empty());
+          let context = format!("cannot read {}", quote(&self.name));
+          return Some(Err(with_context(error, &context)));
+        }
+      }
+      self.line += 1;
+      if line.last() == Some(&b'\n') {
+        line.pop();
+      }
+      if line.is_empty() || line.starts_with(b"#") {
+        continue;
+      }
+      return Some(ManifestReader::parse(&line).ok_or_else(|| {
+        Error::new(
+          ErrorKind::InvalidData,
+          format!("{}:{}: invalid manifest line", quote(&self.name), self.line),
+        )
+      }));
+    }
+  }
+}
+
+/// ## Format a time as seconds and nanoseconds since the epoch.
+///
+/// ### Arguments:
+/// * `time` - The time.
+///
+/// ### Returns:
+/// * `String` - The time, e.g. `1700000000.000000001` or `-1.500000000`.
+fn format_time(time: SystemTime) -> String {
+  let (sign, since) = match time.duration_since(SystemTime::UNIX_EPOCH) {
+    Ok(since) => ("", since),
+    Err(error) => ("-", error.duration()),
+  };
+  format!("{}{}.{:09}", sign, since.as_secs(), since.subsec_nanos())
+}
+
+/// ## Parse a time written by `format_time`.
+///
+/// ### Arguments:
+/// * `text` - The time.
+///
+/// ### Returns:
+/// * `Option<SystemTime>` - The time, or `None` if it is not valid.
+fn parse_time(text: &str) -> Option<SystemTime> {
+  let (negative, text) = match text.strip_prefix('-') {
+    Some(text) => (true, text),
+    None => (false, text),
+  };
+  let (seconds, nanos) = text.split_once('.')?;
+  if nanos.len() != 9
+    || !(seconds.bytes().chain(nanos.bytes())).all(|b| b.is_ascii_digit())
+  {
+    return None;
+  }
+  let since = Duration::new(seconds.parse().ok()?, nanos.parse().ok()?);
+  if negative {
+    SystemTime::UNIX_EPOCH.checked_sub(since)
+  } else {
+    SystemTime::UNIX_EPOCH.checked_add(since)
+  }
+}
+
+/// ## Escape a path for a manifest line.
+///
+/// ### Arguments:
+/// * `path` - The bytes of the path.
+///
+/// ### Returns:
+/// * `String` - The escaped path.
+fn escape(path: &[u8]) -> String {
+  let mut escaped = String::new();
+  for chunk in path.utf8_chunks() {
+    for c in chunk.valid().chars() {
+      match c {
+        '\\' => escaped.push_str("\\\\"),
+        '\t' => escaped.push_str("\\t"),
+        '\n' => escaped.push_str("\\n"),
+        '\r' => escaped.push_str("\\r"),
+        c if c.is_ascii_control() => {
+          escaped.push_str(&format!("\\x{:02x}", c as u8))
+        }
+        c => escaped.push(c),
+      }
+    }
+    for byte in chunk.invalid() {
+      escaped.push_str(&format!("\\x{:02x}", byte));
+    }
+  }
+  escaped
+}
+
+/// ## Undo `escape`.
+///
+/// ### Arguments:
+/// * `escaped` - The escaped path.
+///
+/// ### Returns:
+/// * `Option<Vec<u8>>` - The bytes of the path, or `None` if an escape is
+///   not valid.
+fn unescape(escaped: &[u8]) -> Option<Vec<u8>> {
+  let mut path = Vec::with_capacity(escaped.len());
+  let mut bytes = escaped.iter();
+  while let Some(&byte) = bytes.next() {
+    if byte != b'\\' {
+      path.push(byte);
+      continue;
+    }
+    path.push(match bytes.next()? {
+      b'\\' => b'\\',
+      b't' => b'\t',
+      b'n' => b'\n',
+      b'r' => b'\r',
+      b'x' => {
+        let digits = [*bytes.next()?, *bytes.next()?];
+        u8::from_str_radix(std::str::from_utf8(&digits).ok()?, 16).ok()?
+      }
+      _ => return None,
+    });
+  }
+  (!path.is_empty()).then_some(path)
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  /// ## A time in seconds and nanoseconds since the epoch.
+  fn at(seconds: i64, nanos: u32) -> SystemTime {
+    let since = Duration::new(seconds.unsigned_abs(), 0);
+    let whole = if seconds < 0 {
+      SystemTime::UNIX_EPOCH - since
+    } else {
+      SystemTime::UNIX_EPOCH + since
+    };
+    whole + Duration::from_nanos(nanos.into())
+  }
+
+  #[test]
+  fn times_keep_their_nanoseconds() {
+    for (time, text) in [
+      (at(0, 0), "0.000000000"),
+      (at(1_700_000_000, 1), "1700000000.000000001"),
+      (at(1_700_000_000, 999_999_999), "1700000000.999999999"),
+      (at(-1, 500_000_000), "-0.500000000"),
+      (at(-2, 500_000_000), "-1.500000000"),
+      (at(-315_619_200, 500_000_000), "-315619199.500000000"),
+    ] {
+      assert_eq!(format_time(time), text);
+      assert_eq!(parse_time(text), Some(time), "{}", text);
+    }
+  }
+
+  #[test]
+  fn bad_times_are_rejected() {
+    for bad in [
+      "",
+      "1",
+      "1.5",
+      "1.0000000000",
+      "+1.000000000",
+      "1.00000000a",
+      "-.000000000",
+      "1 .000000000",
+      "99999999999999999999.000000000",
+    ] {
+      assert_eq!(parse_time(bad), None, "{}", bad);
+    }
+  }
+
+  #[test]
+  fn paths_are_escaped_to_a_single_field() {
+    for (path, text) in [
+      (&b"src/main.rs"[..], "src/main.rs"),
+      (b"tab\there", "tab\\there"),
+      (b"new\nline\r", "new\\nline\\r"),
+      (b"back\\slash", "back\\\\slash"),
+      (b"bell\x07 del\x7f", "bell\\x07 del\\x7f"),
+      (b"caf\xc3\xa9", "caf\u{e9}"),
+      (b"bad\xff\xfe", "bad\\xff\\xfe"),
+    ] {
+      assert_eq!(escape(path), text);
+      assert_eq!(unescape(text.as_bytes()).as_deref(), Some(path), "{}", text);
+    }
+  }
+
+  #[test]
+  fn bad_escapes_are_rejected() {
+    for bad in ["", "a\\", "a\\q", "a\\x4", "a\\xzz", "a\\x\u{e9}"] {
+      assert_eq!(unescape(bad.as_bytes()), None, "{}", bad);
+    }
+  }
+
+  #[test]
+  fn manifests_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let name = dir.path().join("manifest");
+    let entries = [
+      Entry {
+        path: PathBuf::from("a\tb"),
+        times: SavedTimes {
+          accessed: at(1_000_000_000, 123_456_789),
+          modified: at(-1_000_000_000, 1),
+          size: 42,
+        },
+      },
+      Entry {
+        path: PathBuf::from(OsString::from_vec(b"dir/\xff\n".to_vec())),
+        times: SavedTimes {
+          accessed: at(0, 0),
+          modified: at(1_100_000_000, 0),
+          size: 0,
+        },
+      },
+    ];
+    let mut manifest = ManifestWriter::create(&name).unwrap();
+    for entry in &entries {
+      manifest.write(entry).unwrap();
+    }
+    manifest.finish().unwrap();
+    let read: Vec<_> = ManifestReader::open(&name)
+      .unwrap()
+      .map(Result::unwrap)
+      .collect();
+    assert_eq!(read.len(), entries.len());
+    for (read, entry) in read.iter().zip(&entries) {
+      assert_eq!(read.path, entry.path);
+      assert_eq!(read.times, entry.times);
+    }
+  }
+
+  #[test]
+  fn bad_lines_are_reported_and_skipped() {
+    let dir = TempDir::new().unwrap();
+    let name = dir.path().join("manifest");
+    let lines = [
+      HEADER,
+      "",
+      "# a comment",
+      "0.000000000\t0.000000000\t0",
+      "0.000000000\t0.000000000\t0\ta",
+    ];
+    std::fs::write(&name, lines.join("\n")).unwrap();
+    let mut manifest = ManifestReader::open(&name).unwrap();
+    let error = manifest.next().unwrap().err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().ends_with(":4: invalid manifest line"));
+    assert_eq!(manifest.next().unwrap().unwrap().path, Path::new("a"));
+    assert!(manifest.next().is_none());
+  }
+}
@@ -0,0 +1,295 @@
+//! # File modes
+//!
+//! Parse the permission modes given to files and directories that are
+//! created, either in octal or symbolically as understood by `chmod`.
+// Imports. -------------------------------------------------------------------
+use std::{
+  fs,
+  io::{Error, ErrorKind},
+};
+
+// Constants. -----------------------------------------------------------------
+
+/// The bits a mode may set.
+const ALL: u32 = 0o7777;
+
+/// The read, write and execute bits for everyone.
+const READ: u32 = 0o444;
+const WRITE: u32 = 0o222;
+const EXECUTE: u32 = 0o111;
+
+/// The set-id and sticky bits.
+const SET_ID: u32 = 0o6000;
+const STICKY: u32 = 0o1000;
+
+// Types. ---------------------------------------------------------------------
+
+/// A mode for a newly created file or directory.
+#[derive(Clone, Debug)]
+pub enum Mode {
+  /// An octal mode, e.g. `0600`.
+  Octal(u32),
+  /// A list of symbolic changes, e.g. `u=rw,go=`.
+  Symbolic(Vec<Change>),
+}
+
+/// A single symbolic change, such as the `g+w` in `u=rw,g+w`.
+#[derive(Clone, Debug)]
+pub struct Change {
+  /// The bits of the users that are changed, or 0 if none were named.
+  who: u32,
+  /// The operator, one of `+`, `-` or `=`.
+  operator: char,
+  /// The permissions to add, remove or set.
+  permissions: Permissions,
+}
+
+/// The permissions in a symbolic change.
+#[derive(Clone, Debug)]
+enum Permissions {
+  /// Permission letters, where `X` is kept apart as it depends on the mode.
+  Bits {
+    bits: u32,
+    conditional_execute: bool,
+  },
+  /// The permissions the user, group or others already have.
+  Copy(u32),
+}
+
+// Functions. -----------------------------------------------------------------
+
+/// ## Parse a mode.
+///
+/// ### Arguments:
+/// * `input` - The mode to parse, e.g. `755`, `0700` or `u=rw,go=r`.
+///
+/// ### Returns:
+/// * `Result<Mode, Error>` - The parsed mode.
+pub fn parse_mode(input: &str) -> Result<Mode, Error> {
+  let invalid =
+    || Error::new(ErrorKind::InvalidInput, format!("invalid mode '{}'", input));
+  if !input.is_empty() && input.bytes().all(|b| b.is_ascii_digit()) {
+    return u32::from_str_radix(input, 8)
+      .ok()
+      .filter(|mode| *mode <= ALL)
+      .map(Mode::Octal)
+      .ok_or_else(invalid);
+  }
+  let mut changes = Vec::new();
+  for clause in input.split(',') {
+    let mut chars = clause.chars().peekable();
+    let mut who = 0;
+    while let Some(bits) = chars.peek().and_then(|c| who_bits(*c)) {
+      who |= bits;
+      chars.next();
+    }
+    // Each clause has at least one operator, each with its own permissions.
+    if chars.peek().is_none() {
+      return Err(invalid());
+    }
+    while let Some(operator) = chars.next() {
+      if !matches!(operator, '+' | '-' | '=') {
+        return Err(invalid());
+      }
+      let permissions = match chars.peek() {
+        Some(&c @ ('u' | 'g' | 'o')) => {
+          chars.next();
+          Permissions::Copy(who_bits(c).unwrap_or_default())
+        }
+        _ => {
+          let (mut bits, mut conditional_execute) = (0, false);
+          while let Some(c) = chars.next_if(|c| !matches!(c, '+' | '-' | '=')) {
+            match c {
+              'r' => bits |= READ,
+              'w' => bits |= WRITE,
+              'x' => bits |= EXECUTE,
+              'X' => conditional_execute = true,
+              's' => bits |= SET_ID,
+              't' => bits |= STICKY,
+              _ => return Err(invalid()),
+            }
+          }
+          Permissions::Bits {
+            bits,
+            conditional_execute,
+          }
+        }
+      };
+      changes.push(Change {
+        who,
+        operator,
+        permissions,
+      });
+    }
+  }
+  Ok(Mode::Symbolic(changes))
+}
+
+/// ## The bits belonging to a user letter.
+///
+/// ### Arguments:
+/// * `c` - One of `u`, `g`, `o` or `a`.
+///
+/// ### Returns:
+/// * `Option<u32>` - The bits, or `None` for any other character.
+fn who_bits(c: char) -> Option<u32> {
+  match c {
+    'u' => Some(0o4700),
+    'g' => Some(0o2070),
+    'o' => Some(0o1007),
+    'a' => Some(ALL),
+    _ => None,
+  }
+}
+
+impl Mode {
+  /// ## Work out the bits of the mode.
+  ///
+  /// As with GNU `mkdir -m`, symbolic changes start from `a=rw` for a file
+  /// or `a=rwx` for a directory, and the umask only filters changes that do
+  /// not name whose permissions they change.
+  ///
+  /// ### Arguments:
+  /// * `directory` - Whether the mode is for a directory.
+  ///
+  /// ### Returns:
+  /// * `u32` - The permission bits to set.
+  pub fn bits(&self, directory: bool) -> u32 {
+    match self {
+      Mode::Octal(mode) => *mode,
+      Mode::Symbolic(_) => self.bits_with_umask(directory, umask()),
+    }
+  }
+
+  /// ## Work out the bits of the mode under a given umask.
+  ///
+  /// ### Arguments:
+  /// * `directory` - Whether the mode is for a directory.
+  /// * `umask` - The permission bits removed from newly created files.
+  ///
+  /// ### Returns:
+  /// * `u32` - The permission bits to set.
+  fn bits_with_umask(&self, directory: bool, umask: u32) -> u32 {
+    let changes = match self {
+      Mode::Octal(mode) => return *mode,
+      Mode::Symbolic(changes) => changes,
+    };
+    let mut mode = if directory { 0o777 } else { 0o666 };
+    for change in changes {
+      let (affected, omitted) = match change.who {
+        0 => (ALL, umask),
+        who => (who, 0),
+      };
+      let value = match change.permissions {
+        Permissions::Bits {
+          bits,
+          conditional_execute,
+        } => {
+          let execute =
+            conditional_execute && (directory || mode & EXECUTE != 0);
+          bits | if execute { EXECUTE } else { 0 }
+        }
+        Permissions::Copy(who) => {
+          // The copied read, write and execute bits apply to everyone.
+          let bits = mode & who & 0o777;
+          let bits = (bits >> 6 | bits >> 3 | bits) & 0o7;
+          bits * 0o111
+        }
+      } & affected
+        & !omitted;
+      mode = match change.operator {
+        '+' => mode | value,
+        '-' => mode & !value,
+        _ => mode & !affected | value,
+      };
+    }
+    mode
+  }
+}
+
+/// ## Read the process umask.
+///
+/// The mask is read from `/proc/self/status`, as `umask` itself can only be
+/// read by changing it, which would briefly affect files created by other
+/// threads. If it cannot be read, the strictest usual mask is assumed.
+///
+/// ### Returns:
+/// * `u32` - The permission bits removed from newly created files.
+fn umask() -> u32 {
+  fs::read_to_string("/proc/self/status")
+    .ok()
+    .and_then(|status| {
+      status
+        .lines()
+        .find_map(|line| line.strip_prefix("Umask:"))
+        .and_then(|mask| u32::from_str_radix(mask.trim(), 8).ok())
+    })
+    .unwrap_or(0o077)
+    & 0o777
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// ## The bits of a mode for a file and a directory, under `umask`.
+  fn bits(input: &str, umask: u32) -> (u32, u32) {
+    let mode = parse_mode(input).unwrap();
+    (
+      mode.bits_with_umask(false, umask),
+      mode.bits_with_umask(true, umask),
+    )
+  }
+
+  #[test]
+  fn octal_modes_ignore_the_umask() {
+    assert_eq!(bits("0600", 0o022), (0o600, 0o600));
+    assert_eq!(bits("4755", 0o077), (0o4755, 0o4755));
+    assert_eq!(parse_mode("7777").unwrap().bits(false), 0o7777);
+  }
+
+  #[test]
+  fn named_users_ignore_the_umask() {
+    assert_eq!(bits("u=rw,go=", 0o022), (0o600, 0o600));
+    assert_eq!(bits("u=rw,go=", 0o000), (0o600, 0o600));
+    assert_eq!(bits("a=rw", 0o077), (0o666, 0o666));
+    assert_eq!(bits("go-w", 0o000), (0o644, 0o755));
+  }
+
+  #[test]
+  fn unnamed_users_are_filtered_by_the_umask() {
+    assert_eq!(bits("=rw", 0o022), (0o644, 0o644));
+    assert_eq!(bits("=rw", 0o077), (0o600, 0o600));
+    assert_eq!(bits("=rwx", 0o027), (0o750, 0o750));
+    // Removals are filtered too, so only the user's write bit goes here.
+    assert_eq!(bits("-w", 0o022), (0o466, 0o577));
+  }
+
+  #[test]
+  fn conditional_execute_depends_on_the_mode_so_far() {
+    assert_eq!(bits("a=r,a+X", 0o000), (0o444, 0o555));
+    assert_eq!(bits("u+x,a+X", 0o000), (0o777, 0o777));
+  }
+
+  #[test]
+  fn permissions_can_be_copied_from_other_users() {
+    assert_eq!(bits("g=r,u=g", 0o000), (0o446, 0o447));
+    assert_eq!(bits("u=rwx,go=u", 0o022), (0o777, 0o777));
+    assert_eq!(bits("o=,g=o", 0o000), (0o600, 0o700));
+  }
+
+  #[test]
+  fn clauses_may_have_several_operators() {
+    assert_eq!(bits("u=rwx-x+s", 0o000), (0o4666, 0o4677));
+  }
+
+  #[test]
+  fn bad_modes_are_rejected() {
+    for bad in ["", "0800", "17777", "u", "u+q", "u=rw,", "+rw,g", "=u+z"] {
+      let error = parse_mode(bad).unwrap_err();
+      assert_eq!(error.to_string(), format!("invalid mode '{}'", bad));
+    }
+  }
+}
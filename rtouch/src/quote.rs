@@ -0,0 +1,125 @@
+//! # Quoting
+//!
+//! Quote file names in diagnostics the way GNU does, so that any name, even
+//! one with control characters or bytes that are not valid UTF-8, is shown
+//! as a string that can be pasted back into a shell.
+// Imports. -------------------------------------------------------------------
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+// Functions. -----------------------------------------------------------------
+
+/// ## Quote a file name.
+///
+/// The name is put in single quotes, e.g. `'a b'`. A name with a single
+/// quote in it is put in double quotes if nothing else in it is special to
+/// the shell there, e.g. `"it's"`, and otherwise the quote is written as
+/// `'\''`. Characters that cannot be printed, and bytes that are not valid
+/// UTF-8, are written as `$'...'` escapes, e.g. `'a'$'\377''b'`.
+///
+/// ### Arguments:
+/// * `name` - The name to quote.
+///
+/// ### Returns:
+/// * `String` - The quoted name.
+pub fn quote<S: AsRef<OsStr>>(name: S) -> String {
+  let bytes = name.as_ref().as_bytes();
+  let text = String::from_utf8_lossy(bytes);
+  let double = text.contains('\'')
+    && std::str::from_utf8(bytes).is_ok_and(|text| {
+      text
+        .chars()
+        .all(|c| !c.is_control() && !matches!(c, '$' | '`' | '\\' | '"' | '!'))
+    });
+  if double {
+    return format!("\"{}\"", text);
+  }
+  let mut quoted = String::from("'");
+  let mut escaping = false;
+  for chunk in bytes.utf8_chunks() {
+    for c in chunk.valid().chars() {
+      if c.is_control() {
+        if !escaping {
+          quoted.push_str("'$'");
+          escaping = true;
+        }
+        let mut buffer = [0; 4];
+        for byte in c.encode_utf8(&mut buffer).bytes() {
+          quoted.push_str(&escape(byte));
+        }
+        continue;
+      }
+      if escaping {
+        quoted.push_str("''");
+        escaping = false;
+      }
+      match c {
+        '\'' => quoted.push_str("'\\''"),
+        c => quoted.push(c),
+      }
+    }
+    for byte in chunk.invalid() {
+      if !escaping {
+        quoted.push_str("'$'");
+        escaping = true;
+      }
+      quoted.push_str(&escape(*byte));
+    }
+  }
+  quoted.push('\'');
+  quoted
+}
+
+/// ## Escape a byte for a `$'...'` string.
+///
+/// ### Arguments:
+/// * `byte` - The byte to escape.
+///
+/// ### Returns:
+/// * `String` - The escape, e.g. `\n` or `\377`.
+fn escape(byte: u8) -> String {
+  match byte {
+    0x07 => "\\a".to_string(),
+    0x08 => "\\b".to_string(),
+    0x09 => "\\t".to_string(),
+    0x0a => "\\n".to_string(),
+    0x0b => "\\v".to_string(),
+    0x0c => "\\f".to_string(),
+    0x0d => "\\r".to_string(),
+    byte => format!("\\{:03o}", byte),
+  }
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plain_names_are_single_quoted() {
+    assert_eq!(quote("plain"), "'plain'");
+    assert_eq!(quote("a b"), "'a b'");
+    assert_eq!(quote(""), "''");
+  }
+
+  #[test]
+  fn single_quotes_are_double_quoted_if_nothing_else_is_special() {
+    assert_eq!(quote("it's"), "\"it's\"");
+    assert_eq!(quote("it's $x"), "'it'\\''s $x'");
+    assert_eq!(quote("'!'"), "''\\''!'\\'''");
+  }
+
+  #[test]
+  fn control_characters_are_escaped() {
+    assert_eq!(quote("tab\there"), "'tab'$'\\t''here'");
+    assert_eq!(quote("x\ny"), "'x'$'\\n''y'");
+    assert_eq!(quote("\u{1}"), "''$'\\001'");
+    assert_eq!(quote("\r\u{7f}"), "''$'\\r\\177'");
+  }
+
+  #[test]
+  fn invalid_utf8_is_escaped_byte_by_byte() {
+    assert_eq!(quote(OsStr::from_bytes(b"a\xffb")), "'a'$'\\377''b'");
+    assert_eq!(quote(OsStr::from_bytes(b"\xc3")), "''$'\\303'");
+  }
+}
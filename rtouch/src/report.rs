@@ -0,0 +1,186 @@
+//! # Reports
+//!
+//! Describe what was done to each file, as lines of text for `-v` or as JSON
+//! lines for `--json`.
+// Imports. -------------------------------------------------------------------
+use chrono::{DateTime, Local, SecondsFormat};
+use rtouch::{quote, Action, Timestamps, TouchOutcome};
+use serde_json::{json, Value};
+use std::{io::Error, path::Path, time::SystemTime};
+
+// Types. ---------------------------------------------------------------------
+
+/// What was done to a file, or would have been with `--dry-run`.
+pub enum Report {
+  /// The file was touched.
+  Touched(TouchOutcome),
+  /// The file's times were saved to a manifest, and left alone.
+  Saved(Timestamps),
+}
+
+// Functions. -----------------------------------------------------------------
+
+impl Report {
+  /// ## Describe what was done.
+  ///
+  /// ### Arguments:
+  /// * `dry_run` - Whether it was only worked out.
+  ///
+  /// ### Returns:
+  /// * `&str` - What was done, e.g. "created" or "would save".
+  fn describe(&self, dry_run: bool) -> &'static str {
+    match (self, dry_run) {
+      (Report::Touched(outcome), _) => describe(outcome.action, dry_run),
+      (Report::Saved(_), false) => "saved",
+      (Report::Saved(_), true) => "would save",
+    }
+  }
+}
+
+/// ## Describe an action.
+///
+/// ### Arguments:
+/// * `action` - The action.
+/// * `dry_run` - Whether the action was only worked out.
+///
+/// ### Returns:
+/// * `&str` - The action, e.g. "created" or "would create".
+fn describe(action: Action, dry_run: bool) -> &'static str {
+  match (action, dry_run) {
+    (Action::Created, false) => "created",
+    (Action::Updated, false) => "updated",
+    (Action::Skipped, false) => "skipped",
+    (Action::Created, true) => "would create",
+    (Action::Updated, true) => "would update",
+    (Action::Skipped, true) => "would skip",
+  }
+}
+
+/// ## Describe a touched file for `-v`.
+///
+/// Only the times that were set are listed, e.g. "updated 'a' (access
+/// 2024-05-01 12:00:00.000000000 +0200)".
+///
+/// ### Arguments:
+/// * `file` - The file.
+/// * `report` - What was done to it.
+/// * `dry_run` - Whether nothing was changed.
+///
+/// ### Returns:
+/// * `String` - The description.
+pub fn verbose(file: &Path, report: &Report, dry_run: bool) -> String {
+  let (set, skipped) = match report {
+    Report::Touched(outcome) => {
+      (outcome.set, outcome.action == Action::Skipped)
+    }
+    Report::Saved(times) => (*times, false),
+  };
+  let times: Vec<String> = [("access", set.accessed), ("modify", set.modified)]
+    .into_iter()
+    .filter_map(|(name, time)| {
+      time.map(|time| {
+        let time = DateTime::<Local>::from(time);
+        format!("{} {}", name, time.format("%Y-%m-%d %H:%M:%S%.9f %z"))
+      })
+    })
+    .collect();
+  let description = format!("{} {}", report.describe(dry_run), quote(file));
+  if skipped || times.is_empty() {
+    return description;
+  }
+  format!("{} ({})", description, times.join(", "))
+}
+
+/// ## Describe a directory created by `-p`, for `-v`.
+///
+/// ### Arguments:
+/// * `dir` - The directory.
+/// * `dry_run` - Whether nothing was changed.
+///
+/// ### Returns:
+/// * `String` - The description, e.g. "created directory 'a'".
+pub fn directory(dir: &Path, dry_run: bool) -> String {
+  format!(
+    "{} directory {}",
+    describe(Action::Created, dry_run),
+    quote(dir)
+  )
+}
+
+/// ## Describe a file as a JSON line for `--json`.
+///
+/// A time that was not set is reported with its old value in `new`, and a
+/// skipped file keeps its old times. As JSON strings must be valid Unicode,
+/// bytes in a file name that are not valid UTF-8 are replaced with U+FFFD.
+///
+/// ### Arguments:
+/// * `file` - The file.
+/// * `result` - What was done to it, or why that failed.
+/// * `dry_run` - Whether nothing was changed.
+///
+/// ### Returns:
+/// * `String` - The JSON object, on a single line.
+pub fn json(
+  file: &Path,
+  result: &Result<Report, Error>,
+  dry_run: bool,
+) -> String {
+  let file = file.to_string_lossy();
+  let line = match result {
+    Ok(report) => {
+      let (old, new) = match report {
+        Report::Touched(outcome) if outcome.action == Action::Skipped => {
+          (outcome.old, outcome.old)
+        }
+        Report::Touched(outcome) => (
+          outcome.old,
+          Timestamps {
+            accessed: outcome.set.accessed.or(outcome.old.accessed),
+            modified: outcome.set.modified.or(outcome.old.modified),
+            now: outcome.set.now,
+          },
+        ),
+        Report::Saved(times) => (*times, *times),
+      };
+      json!({
+        "file": file,
+        "action": report.describe(false),
+        "dry_run": dry_run,
+        "old": json_times(old),
+        "new": json_times(new),
+        "error": null,
+      })
+    }
+    Err(error) => json!({
+      "file": file,
+      "action": "failed",
+      "dry_run": dry_run,
+      "old": null,
+      "new": null,
+      "error": {
+        "kind": format!("{:?}", error.kind()),
+        "message": error.to_string(),
+      },
+    }),
+  };
+  line.to_string()
+}
+
+/// ## Convert times to JSON.
+///
+/// ### Arguments:
+/// * `times` - The times, where unknown times are `None`.
+///
+/// ### Returns:
+/// * `Value` - An object with RFC 3339 `atime` and `mtime` strings, or null.
+fn json_times(times: Timestamps) -> Value {
+  let format = |time: Option<SystemTime>| {
+    time.map(|time| {
+      DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Nanos, true)
+    })
+  };
+  json!({
+    "atime": format(times.accessed),
+    "mtime": format(times.modified),
+  })
+}
@@ -0,0 +1,120 @@
+//! # Times
+//!
+//! Parse the times given to `-d` and `-t` into system times, keeping
+//! nanosecond precision and times before the epoch.
+// Imports. -------------------------------------------------------------------
+use crate::{date, TouchError};
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use std::time::{Duration, SystemTime};
+
+// Functions. -----------------------------------------------------------------
+
+/// ## Parse a time string.
+///
+/// The fixed ISO 8601 layouts are tried first, followed by the free-form
+/// date strings understood by GNU `date -d`, e.g. "yesterday 14:00" or
+/// "@1700000000". The result keeps nanosecond precision and may be before
+/// the epoch.
+///
+/// ### Arguments:
+/// * `time` - The time string to parse.
+/// * `now` - The time relative dates are resolved against.
+///
+/// ### Returns:
+/// * `Result<SystemTime, TouchError>` - The parsed time.
+pub fn parse_time(
+  time: &str,
+  now: SystemTime,
+) -> Result<SystemTime, TouchError> {
+  let formats = [
+    // ISO 8601
+    "%Y-%m-%dT%H:%M:%S.%3f%z",
+    "%Y-%m-%dT%H:%M:%S%z",
+    // With SPACE as time separator
+    "%Y-%m-%d %H:%M:%S.%3f%z",
+    "%Y-%m-%d %H:%M:%S%z",
+    // Without time separator
+    "%Y-%m-%d%H:%M:%S.%3f%z",
+    "%Y-%m-%d%H:%M:%S%z",
+  ];
+  let offset = match formats
+    .iter()
+    .find_map(|format| DateTime::parse_from_str(time, format).ok())
+  {
+    Some(offset) => offset,
+    None => date::parse_date(time, &DateTime::<Local>::from(now))
+      .map_err(|error| TouchError::InvalidTime(error.to_string()))?,
+  };
+  // Times before the epoch and fractions of a second are both preserved.
+  Ok(SystemTime::from(offset))
+}
+
+/// ## Parse a POSIX time stamp.
+///
+/// The stamp has the form `[[CC]YY]MMDDhhmm[.ss]` and is interpreted in local
+/// time, as set by the `TZ` environment variable. A two digit year from 69 to
+/// 99 is in the 1900s and from 00 to 68 is in the 2000s, and the year
+/// defaults to the current one.
+///
+/// ### Arguments:
+/// * `stamp` - The time stamp to parse.
+/// * `now` - The time the current year is taken from.
+///
+/// ### Returns:
+/// * `Result<SystemTime, TouchError>` - The parsed time.
+pub fn parse_stamp(
+  stamp: &str,
+  now: SystemTime,
+) -> Result<SystemTime, TouchError> {
+  let invalid =
+    || TouchError::InvalidTime(format!("invalid date format '{}'", stamp));
+  let (digits, seconds) = match stamp.split_once('.') {
+    Some((digits, seconds)) => (digits, seconds),
+    None => (stamp, "00"),
+  };
+  if !digits.bytes().all(|b| b.is_ascii_digit())
+    || seconds.len() != 2
+    || !seconds.bytes().all(|b| b.is_ascii_digit())
+  {
+    return Err(invalid());
+  }
+  // Each field is two digits, counted back from the end of the stamp.
+  let length = digits.len();
+  let field = |end: usize| -> u32 {
+    digits[length - end..length - end + 2]
+      .parse()
+      .unwrap_or_default()
+  };
+  let year = match length {
+    8 => DateTime::<Local>::from(now).year(),
+    10 => match field(10) as i32 {
+      year @ 69..=99 => 1900 + year,
+      year => 2000 + year,
+    },
+    12 => (field(12) * 100 + field(10)) as i32,
+    _ => return Err(invalid()),
+  };
+  // A leap second is accepted and rolls over into the next minute.
+  let seconds: u32 = seconds.parse().unwrap_or_default();
+  if seconds > 60 {
+    return Err(invalid());
+  }
+  let leap = seconds == 60;
+  let date_time = NaiveDate::from_ymd_opt(year, field(8), field(6))
+    .and_then(|date| date.and_hms_opt(field(4), field(2), seconds.min(59)))
+    .ok_or_else(invalid)?;
+  let date_time =
+    date::resolve_local(&date_time, &DateTime::<Local>::from(now)).map_err(
+      |error| {
+        TouchError::InvalidTime(format!(
+          "invalid date format '{}': {}",
+          stamp, error
+        ))
+      },
+    )?;
+  let system_time = SystemTime::from(date_time);
+  if leap {
+    return Ok(system_time + Duration::from_secs(1));
+  }
+  Ok(system_time)
+}
@@ -0,0 +1,92 @@
+//! # Recursive walks
+//!
+//! Walk a directory, as `-R` does, yielding every entry whose times are to be
+//! set, so that a tree can be touched from Rust as well.
+// Imports. -------------------------------------------------------------------
+use glob::{MatchOptions, Pattern};
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+// Types. ---------------------------------------------------------------------
+
+/// How a directory is walked.
+pub struct Walk<'a> {
+  /// Only files matching one of these are yielded, if there are any.
+  pub include: &'a [Pattern],
+  /// Entries matching one of these are skipped, along with everything below
+  /// them.
+  pub exclude: &'a [Pattern],
+  /// How far below the root to go, where the root itself is at depth 0.
+  pub max_depth: Option<usize>,
+  /// Whether to follow symbolic links to directories.
+  pub follow_links: bool,
+  /// Whether directories themselves are yielded.
+  pub directories: bool,
+}
+
+// Functions. -----------------------------------------------------------------
+
+impl Walk<'_> {
+  /// ## Walk a directory.
+  ///
+  /// The root is always yielded, unless directories are not. Below it,
+  /// `include` only applies to entries that are not directories, so that a
+  /// pattern such as `*.c` still finds the files inside subdirectories.
+  ///
+  /// ### Arguments:
+  /// * `root` - The directory to walk.
+  ///
+  /// ### Returns:
+  /// * `impl Iterator<Item = Result<PathBuf, walkdir::Error>>` - The paths
+  ///   to update, and any errors reading the tree.
+  pub fn walk<'a>(
+    &'a self,
+    root: &'a Path,
+  ) -> impl Iterator<Item = Result<PathBuf, walkdir::Error>> + 'a {
+    let mut walker = WalkDir::new(root).follow_links(self.follow_links);
+    if let Some(max_depth) = self.max_depth {
+      walker = walker.max_depth(max_depth);
+    }
+    walker
+      .into_iter()
+      .filter_entry(move |entry| {
+        entry.depth() == 0 || !matches(self.exclude, root, entry)
+      })
+      .filter_map(move |entry| {
+        let entry = match entry {
+          Ok(entry) => entry,
+          Err(error) => return Some(Err(error)),
+        };
+        let wanted = if entry.file_type().is_dir() {
+          self.directories
+        } else {
+          self.include.is_empty() || matches(self.include, root, &entry)
+        };
+        wanted.then(|| Ok(entry.into_path()))
+      })
+  }
+}
+
+/// ## Check an entry against a list of patterns.
+///
+/// A pattern matches either the entry's name, e.g. `*.o`, or its path
+/// relative to the root, e.g. `build/*.o`, where `*` does not match `/`.
+///
+/// ### Arguments:
+/// * `patterns` - The patterns to check.
+/// * `root` - The directory being walked.
+/// * `entry` - The entry to check.
+///
+/// ### Returns:
+/// * `bool` - Whether any of the patterns match.
+fn matches(patterns: &[Pattern], root: &Path, entry: &DirEntry) -> bool {
+  let options = MatchOptions {
+    require_literal_separator: true,
+    ..MatchOptions::new()
+  };
+  let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+  patterns.iter().any(|pattern| {
+    pattern.matches_path_with(Path::new(entry.file_name()), options)
+      || pattern.matches_path_with(relative, options)
+  })
+}
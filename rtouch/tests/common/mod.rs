@@ -0,0 +1,134 @@
+//! # Common helpers
+//!
+//! Run rtouch and read back the times it set, for each of the integration
+//! test suites. Not every suite uses every helper.
+#![allow(dead_code)]
+// Imports. -------------------------------------------------------------------
+use std::{
+  fs,
+  os::unix::{fs::PermissionsExt, process::CommandExt},
+  path::Path,
+  process::{Command, Output},
+  time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+
+// Functions. -----------------------------------------------------------------
+
+/// ## Prepare to run rtouch in a directory.
+///
+/// Times are read and shown in UTC, and `SOURCE_DATE_EPOCH` is unset, so that
+/// the results do not depend on the environment the tests run in.
+///
+/// ### Arguments:
+/// * `dir` - The directory to run in.
+/// * `args` - The arguments.
+///
+/// ### Returns:
+/// * `Command` - The command, ready to run.
+pub fn command(dir: &Path, args: &[&str]) -> Command {
+  let mut command = Command::new(env!("CARGO_BIN_EXE_rtouch"));
+  command
+    .current_dir(dir)
+    .env("TZ", "UTC")
+    .env_remove("SOURCE_DATE_EPOCH")
+    .args(args);
+  command
+}
+
+/// ## Run rtouch in a directory.
+///
+/// ### Arguments:
+/// * `dir` - The directory to run in.
+/// * `args` - The arguments.
+///
+/// ### Returns:
+/// * `Output` - What rtouch printed, and its exit status.
+pub fn rtouch(dir: &Path, args: &[&str]) -> Output {
+  command(dir, args).output().unwrap()
+}
+
+/// ## Run rtouch in a directory as the user `nobody`.
+///
+/// The directory is made writable by everyone, and rtouch is run from a copy
+/// that `nobody` can reach. Only root can switch users, so for anyone else
+/// nothing is run.
+///
+/// ### Arguments:
+/// * `dir` - The directory to run in.
+/// * `args` - The arguments.
+///
+/// ### Returns:
+/// * `Option<Output>` - What rtouch printed, and its exit status, or `None`
+///   if the tests are not run as root.
+pub fn rtouch_as_nobody(dir: &Path, args: &[&str]) -> Option<Output> {
+  // SAFETY: `geteuid` has no preconditions and cannot fail.
+  if unsafe { libc::geteuid() } != 0 {
+    return None;
+  }
+  let bin = TempDir::new().unwrap();
+  let copy = bin.path().join("rtouch");
+  fs::copy(env!("CARGO_BIN_EXE_rtouch"), &copy).unwrap();
+  for (path, mode) in [(bin.path(), 0o755), (dir, 0o777)] {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+  }
+  let output = Command::new(copy)
+    .current_dir(dir)
+    .env("TZ", "UTC")
+    .env_remove("SOURCE_DATE_EPOCH")
+    .uid(65534)
+    .gid(65534)
+    .args(args)
+    .output()
+    .unwrap();
+  Some(output)
+}
+
+/// ## Run rtouch, expecting it to succeed without printing anything.
+pub fn succeed(dir: &Path, args: &[&str]) {
+  let output = rtouch(dir, args);
+  assert_eq!(stderr(&output), "");
+  assert_eq!(output.status.code(), Some(0));
+}
+
+/// ## What rtouch printed to standard output.
+pub fn stdout(output: &Output) -> String {
+  String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// ## What rtouch printed to standard error.
+pub fn stderr(output: &Output) -> String {
+  String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// ## A time in seconds and nanoseconds since the epoch.
+pub fn at(seconds: i64, nanos: u32) -> SystemTime {
+  let since = Duration::new(seconds.unsigned_abs(), 0);
+  let whole = if seconds < 0 {
+    SystemTime::UNIX_EPOCH - since
+  } else {
+    SystemTime::UNIX_EPOCH + since
+  };
+  whole + Duration::from_nanos(nanos.into())
+}
+
+/// ## The access and modification times of a file.
+pub fn times(path: &Path) -> (SystemTime, SystemTime) {
+  let metadata = fs::metadata(path).unwrap();
+  (metadata.accessed().unwrap(), metadata.modified().unwrap())
+}
+
+/// ## The access time of a file.
+pub fn accessed(path: &Path) -> SystemTime {
+  times(path).0
+}
+
+/// ## The modification time of a file.
+pub fn modified(path: &Path) -> SystemTime {
+  times(path).1
+}
+
+/// ## The permission bits of a file, or of a symbolic link itself.
+pub fn mode(path: &Path) -> u32 {
+  fs::symlink_metadata(path).unwrap().permissions().mode() & 0o7777
+}
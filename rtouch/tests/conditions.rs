@@ -0,0 +1,121 @@
+//! # --if-older-than and --if-newer-than
+//!
+//! Check that only the files whose modification times meet the conditions
+//! are updated, in a temporary directory created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{at, command, modified, rtouch, stderr, stdout};
+use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt};
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Create files modified at different times.
+///
+/// `old` is modified at 1000000000, `ref` at 1100000000 and `new` at
+/// 1200000000.
+///
+/// ### Returns:
+/// * `TempDir` - The directory holding the files, removed when dropped.
+fn files() -> TempDir {
+  let dir = TempDir::new().unwrap();
+  for (name, seconds) in [
+    ("old", 1_000_000_000),
+    ("ref", 1_100_000_000),
+    ("new", 1_200_000_000),
+  ] {
+    let time = format!("@{}", seconds);
+    assert!(rtouch(dir.path(), &["-d", &time, name]).status.success());
+  }
+  dir
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn only_files_older_than_a_reference_are_updated() {
+  let dir = files();
+  let args = ["--if-older-than", "ref", "-d", "@1300000000", "old", "new"];
+  assert!(rtouch(dir.path(), &args).status.success());
+  assert_eq!(modified(&dir.path().join("old")), at(1_300_000_000, 0));
+  assert_eq!(modified(&dir.path().join("new")), at(1_200_000_000, 0));
+}
+
+#[test]
+fn only_files_newer_than_a_date_are_updated() {
+  let dir = files();
+  let args = ["--if-newer-than=2004-11-09 11:33:20", "-d", "@1300000000"];
+  let output =
+    rtouch(dir.path(), &[&args[..], &["old", "ref", "new"]].concat());
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("old")), at(1_000_000_000, 0));
+  // The times are compared strictly, so a file at the limit is skipped.
+  assert_eq!(modified(&dir.path().join("ref")), at(1_100_000_000, 0));
+  assert_eq!(modified(&dir.path().join("new")), at(1_300_000_000, 0));
+}
+
+#[test]
+fn both_conditions_must_hold() {
+  let dir = files();
+  let args = ["--if-newer-than=@1000000000", "--if-older-than=@1200000000"];
+  let output = rtouch(
+    dir.path(),
+    &[&args[..], &["-d", "@0", "old", "ref", "new"]].concat(),
+  );
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("old")), at(1_000_000_000, 0));
+  assert_eq!(modified(&dir.path().join("ref")), at(0, 0));
+  assert_eq!(modified(&dir.path().join("new")), at(1_200_000_000, 0));
+}
+
+#[test]
+fn missing_files_are_not_created() {
+  let dir = files();
+  let output = rtouch(dir.path(), &["--if-older-than", "ref", "missing"]);
+  assert!(output.status.success());
+  assert!(!dir.path().join("missing").exists());
+}
+
+#[test]
+fn skipped_files_are_reported_when_verbose() {
+  let dir = files();
+  let args = ["-v", "--if-older-than", "ref", "-d", "@0", "old", "new"];
+  let output = rtouch(dir.path(), &args);
+  assert!(output.status.success());
+  assert_eq!(
+    stdout(&output),
+    "rtouch: updated 'old' (access 1970-01-01 00:00:00.000000000 +0000, \
+     modify 1970-01-01 00:00:00.000000000 +0000)\n\
+     rtouch: skipped 'new'\n"
+  );
+}
+
+#[test]
+fn a_limit_that_is_neither_a_file_nor_a_date_is_an_error() {
+  let dir = files();
+  let output = rtouch(dir.path(), &["--if-older-than", "nowhere", "old"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: 'nowhere' is neither a file nor a valid date\n"
+  );
+  assert_eq!(modified(&dir.path().join("old")), at(1_000_000_000, 0));
+}
+
+#[test]
+fn references_need_not_have_utf8_names() {
+  let dir = files();
+  let name = OsStr::from_bytes(b"x\xffy");
+  fs::rename(dir.path().join("ref"), dir.path().join(name)).unwrap();
+  let output = command(dir.path(), &["--if-older-than"])
+    .arg(name)
+    .args(["-d", "@1300000000", "old", "new"])
+    .output()
+    .unwrap();
+  assert_eq!(stderr(&output), "");
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("old")), at(1_300_000_000, 0));
+  assert_eq!(modified(&dir.path().join("new")), at(1_200_000_000, 0));
+}
@@ -0,0 +1,114 @@
+//! # --files-from and --files0-from
+//!
+//! Check that the files to update can be read from a list, from a file or
+//! standard input, in a temporary directory created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{at, command, rtouch, stderr, succeed, times};
+use std::{
+  fs,
+  io::Write,
+  path::Path,
+  process::{Output, Stdio},
+};
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Run rtouch with a list on its standard input.
+///
+/// ### Arguments:
+/// * `dir` - The directory to run in.
+/// * `args` - The arguments.
+/// * `input` - What to write to standard input.
+///
+/// ### Returns:
+/// * `Output` - What rtouch printed, and its exit status.
+fn rtouch_with_input(dir: &Path, args: &[&str], input: &[u8]) -> Output {
+  let mut child = command(dir, args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  child.stdin.take().unwrap().write_all(input).unwrap();
+  child.wait_with_output().unwrap()
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn files_from_reads_one_name_per_line() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("list"), "a\nb c\nd").unwrap();
+  succeed(dir.path(), &["--files-from", "list", "-d", "@0"]);
+  for name in ["a", "b c", "d"] {
+    assert_eq!(times(&dir.path().join(name)), (at(0, 0), at(0, 0)));
+  }
+}
+
+#[test]
+fn files0_from_allows_newlines_in_names() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("list"), "new\nline\0plain\0").unwrap();
+  succeed(dir.path(), &["--files0-from", "list", "-d", "@0"]);
+  for name in ["new\nline", "plain"] {
+    assert_eq!(times(&dir.path().join(name)), (at(0, 0), at(0, 0)));
+  }
+  assert!(!dir.path().join("new").exists());
+}
+
+#[test]
+fn lists_are_read_from_standard_input() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch_with_input(dir.path(), &["--files-from", "-"], b"a\nb\n");
+  assert!(output.status.success());
+  assert!(dir.path().join("a").exists());
+  assert!(dir.path().join("b").exists());
+  let output =
+    rtouch_with_input(dir.path(), &["--files0-from=-", "-d", "@0"], b"c");
+  assert!(output.status.success());
+  assert_eq!(times(&dir.path().join("c")), (at(0, 0), at(0, 0)));
+}
+
+#[test]
+fn empty_names_are_reported_and_skipped() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("list"), "a\n\nb\n").unwrap();
+  let output = rtouch(dir.path(), &["--files-from", "list"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: 'list':2: invalid zero-length file name\n"
+  );
+  assert!(dir.path().join("a").exists());
+  assert!(dir.path().join("b").exists());
+}
+
+#[test]
+fn missing_lists_are_an_error() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--files0-from", "list"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot open 'list' for reading: No such file or directory\n"
+  );
+}
+
+#[test]
+fn lists_and_operands_conflict() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("list"), "a\n").unwrap();
+  for args in [
+    &["--files-from", "list", "b"][..],
+    &["--files-from", "list", "--files0-from", "list"],
+  ] {
+    let output = rtouch(dir.path(), args);
+    assert_eq!(output.status.code(), Some(2));
+  }
+  assert!(!dir.path().join("a").exists());
+  assert!(!dir.path().join("b").exists());
+}
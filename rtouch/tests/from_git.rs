@@ -0,0 +1,141 @@
+//! # --from-git
+//!
+//! Check that modification times are taken from the history of a repository
+//! created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{accessed, at, modified, rtouch, stderr};
+use git2::{IndexAddOption, Repository, Signature, Time};
+use std::fs;
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Create a repository with two commits.
+///
+/// `a` and `src/b` are committed at 1000000000, then `src/b` is changed at
+/// 1100000000. `untracked` is never committed.
+///
+/// ### Returns:
+/// * `TempDir` - The work tree, removed when dropped.
+fn repository() -> TempDir {
+  let dir = TempDir::new().unwrap();
+  let repository = Repository::init(dir.path()).unwrap();
+  fs::create_dir(dir.path().join("src")).unwrap();
+  fs::write(dir.path().join("a"), "1").unwrap();
+  fs::write(dir.path().join("src/b"), "1").unwrap();
+  commit(&repository, 1_000_000_000);
+  fs::write(dir.path().join("src/b"), "2").unwrap();
+  commit(&repository, 1_100_000_000);
+  fs::write(dir.path().join("untracked"), "").unwrap();
+  dir
+}
+
+/// ## Commit the whole work tree.
+///
+/// ### Arguments:
+/// * `repository` - The repository.
+/// * `seconds` - The commit time, in seconds since the epoch.
+fn commit(repository: &Repository, seconds: i64) {
+  let mut index = repository.index().unwrap();
+  index.add_all(["*"], IndexAddOption::DEFAULT, None).unwrap();
+  index.write().unwrap();
+  let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+  let signature =
+    Signature::new("rtouch", "rtouch@example.com", &Time::new(seconds, 0))
+      .unwrap();
+  let parent = repository
+    .head()
+    .ok()
+    .map(|head| head.peel_to_commit().unwrap());
+  let parents: Vec<_> = parent.iter().collect();
+  repository
+    .commit(
+      Some("HEAD"),
+      &signature,
+      &signature,
+      "commit",
+      &tree,
+      &parents,
+    )
+    .unwrap();
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn files_get_the_time_of_their_last_commit() {
+  let dir = repository();
+  let output = rtouch(dir.path(), &["--from-git", "a", "src/b"]);
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("a")), at(1_000_000_000, 0));
+  assert_eq!(modified(&dir.path().join("src/b")), at(1_100_000_000, 0));
+}
+
+#[test]
+fn directories_get_the_time_of_their_newest_commit() {
+  let dir = repository();
+  let output = rtouch(dir.path(), &["--from-git", "-R", "."]);
+  assert!(output.status.success());
+  assert_eq!(modified(dir.path()), at(1_100_000_000, 0));
+  assert_eq!(modified(&dir.path().join("src")), at(1_100_000_000, 0));
+  assert_eq!(modified(&dir.path().join("a")), at(1_000_000_000, 0));
+}
+
+#[test]
+fn paths_are_resolved_from_any_directory() {
+  let dir = repository();
+  let output = rtouch(&dir.path().join("src"), &["--from-git", "../a", "b"]);
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("a")), at(1_000_000_000, 0));
+  assert_eq!(modified(&dir.path().join("src/b")), at(1_100_000_000, 0));
+}
+
+#[test]
+fn untracked_files_fall_back_to_the_given_time() {
+  let dir = repository();
+  let output = rtouch(
+    dir.path(),
+    &["--from-git", "-d", "@1200000000", "untracked"],
+  );
+  assert!(output.status.success());
+  assert_eq!(
+    modified(&dir.path().join("untracked")),
+    at(1_200_000_000, 0)
+  );
+}
+
+#[test]
+fn access_times_are_left_alone() {
+  let dir = repository();
+  let a = dir.path().join("a");
+  let output = rtouch(dir.path(), &["-d", "@1300000000", "a"]);
+  assert!(output.status.success());
+  let output = rtouch(dir.path(), &["--from-git", "a"]);
+  assert!(output.status.success());
+  assert_eq!(accessed(&a), at(1_300_000_000, 0));
+  assert_eq!(modified(&a), at(1_000_000_000, 0));
+}
+
+#[test]
+fn files_outside_a_repository_fall_back_to_the_given_time() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--from-git", "-d", "@1200000000", "new"]);
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("new")), at(1_200_000_000, 0));
+}
+
+#[test]
+fn only_the_access_time_word_conflicts() {
+  let dir = repository();
+  let a = dir.path().join("a");
+  let output = rtouch(dir.path(), &["--from-git", "--time=mtime", "a"]);
+  assert!(output.status.success());
+  assert_eq!(modified(&a), at(1_000_000_000, 0));
+  let output = rtouch(dir.path(), &["--from-git", "--time=access", "a"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(stderr(&output)
+    .contains("the argument '--time <WORD>' cannot be used with '--from-git'"));
+}
@@ -0,0 +1,458 @@
+//! # GNU conformance
+//!
+//! Run the same arguments through rtouch and GNU `touch`, each in a scratch
+//! directory holding the same files, and compare the times and files that
+//! result, what was printed to standard error and the exit status.
+//!
+//! GNU `touch` is looked for as `touch` on the `PATH`, or wherever
+//! `RTOUCH_GNU_TOUCH` points, and the tests are skipped if it is not GNU.
+//! Both run in the C locale, in UTC unless a case says otherwise.
+//!
+//! The places rtouch differs on purpose are listed in `DIVERGENCES`, and are
+//! checked to still differ, so that the list stays true.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::at;
+use std::{
+  env,
+  fs::{self, File, FileTimes, Permissions},
+  os::unix::fs::{symlink, PermissionsExt},
+  path::{Path, PathBuf},
+  process::Command,
+  time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+
+// Types. ---------------------------------------------------------------------
+
+/// Arguments to run through both programs.
+struct Case {
+  /// The arguments, given to both.
+  args: &'static [&'static str],
+  /// The time zone to run in, if not UTC.
+  zone: Option<&'static str>,
+  /// Whether the times depend on the current time, so that they can only be
+  /// compared to within a few seconds.
+  approximate: bool,
+}
+
+/// A place where rtouch differs from GNU on purpose.
+struct Divergence {
+  /// A case that shows the difference.
+  case: Case,
+  /// Why rtouch differs.
+  reason: &'static str,
+}
+
+/// What running a program left behind.
+#[derive(Debug)]
+struct Outcome {
+  status: Option<i32>,
+  stderr: String,
+  /// Each file in the scratch directory, with its type and times.
+  files: Vec<(PathBuf, &'static str, SystemTime, SystemTime)>,
+}
+
+// Cases. ---------------------------------------------------------------------
+
+/// A case with the given arguments, whose times are exact.
+const fn case(args: &'static [&'static str]) -> Case {
+  Case {
+    args,
+    zone: None,
+    approximate: false,
+  }
+}
+
+/// A case whose times depend on the current time.
+const fn approximate(args: &'static [&'static str]) -> Case {
+  Case {
+    args,
+    zone: None,
+    approximate: true,
+  }
+}
+
+/// A case run in a time zone.
+const fn zoned(zone: &'static str, args: &'static [&'static str]) -> Case {
+  Case {
+    args,
+    zone: Some(zone),
+    approximate: false,
+  }
+}
+
+/// The arguments that rtouch must handle as GNU does. Each runs against the
+/// files made by `scratch`.
+const CASES: &[Case] = &[
+  // Creating and updating files.
+  approximate(&["new"]),
+  approximate(&["file"]),
+  approximate(&["new", "file", "dir"]),
+  case(&["-c", "new"]),
+  approximate(&["-c", "file", "new"]),
+  case(&["-d", "@1200000000", "file", "new"]),
+  case(&["-d", "@1200000000", "dir"]),
+  case(&["-d", "@1200000000", "readonly"]),
+  case(&["-d", "@1200000000", "nodir/new"]),
+  case(&["-d", "@1200000000", "file/new"]),
+  case(&["-d", "@1200000000", "nodir/new", "new"]),
+  case(&["-d", "@1200000000", "--", "-new"]),
+  // -a, -m and --time.
+  case(&["-a", "-d", "@1200000000", "file"]),
+  case(&["-m", "-d", "@1200000000", "file"]),
+  case(&["-a", "-d", "@1200000000", "new"]),
+  approximate(&["-m", "file"]),
+  case(&["--time=atime", "-d", "@1200000000", "file"]),
+  case(&["--time=access", "-d", "@1200000000", "file"]),
+  case(&["--time=use", "-d", "@1200000000", "file"]),
+  case(&["--time=mtime", "-d", "@1200000000", "file"]),
+  case(&["--time=modify", "-d", "@1200000000", "file"]),
+  // -d and parse_time.
+  case(&["-d", "2020-01-01 12:00:00", "file"]),
+  case(&["-d", "2020-01-01 12:00:00.123456789", "file"]),
+  case(&["-d", "2020-01-01T12:00:00+02:00", "file"]),
+  case(&["-d", "2020-01-01T12:00:00,5", "file"]),
+  case(&["-d", "2020-01-01 12:00:00.123+0000", "file"]),
+  case(&["-d", "2020-01-01 12:00 UTC", "file"]),
+  case(&["-d", "2020-01-01 12:00 +0530", "file"]),
+  case(&["-d", "May 1 2024 12:00", "file"]),
+  case(&["-d", "1 May 2024", "file"]),
+  case(&["-d", "2024-05-01 5pm", "file"]),
+  case(&["-d", "@-1.5", "file"]),
+  case(&["-d", "1960-06-01", "new"]),
+  case(&["-d", "2020-01-01 +1 day -2 hours", "file"]),
+  approximate(&["-d", "yesterday", "file"]),
+  approximate(&["-d", "now", "file"]),
+  approximate(&["-d", "2 hours ago", "file"]),
+  approximate(&["-d", "1 month ago", "file"]),
+  approximate(&["-d", "next friday", "file"]),
+  approximate(&["-d", "tomorrow 14:00", "file"]),
+  case(&["-d", "bogus", "file", "new"]),
+  case(&["-d", "tomorrow 25:00", "file"]),
+  case(&["-d", "2020-02-30", "file"]),
+  case(&["-d", "", "file"]),
+  // -t.
+  case(&["-t", "202001020304.05", "file"]),
+  case(&["-t", "2001020304", "file"]),
+  case(&["-t", "6901010000", "file"]),
+  case(&["-t", "6801010000", "file"]),
+  case(&["-t", "201612312359.60", "file"]),
+  case(&["-t", "12", "file"]),
+  case(&["-t", "202001010000.61", "file"]),
+  case(&["-t", "202013010000", "file"]),
+  case(&["-t", "2020010100x0", "file"]),
+  case(&["-m", "-t", "202001020304.05", "file"]),
+  // -r.
+  case(&["-r", "file", "new"]),
+  case(&["-r", "file", "dir"]),
+  case(&["-a", "-r", "file", "dir"]),
+  case(&["-m", "-r", "file", "new"]),
+  case(&["-r", "file", "-d", "-1 hour", "new"]),
+  case(&["-r", "missing", "new"]),
+  case(&["-r", "link", "new"]),
+  // Symbolic links and -h.
+  case(&["-d", "@1200000000", "link"]),
+  case(&["-d", "@1200000000", "dangling"]),
+  case(&["-h", "-d", "@1200000000", "link"]),
+  case(&["-h", "-d", "@1200000000", "dangling"]),
+  case(&["-h", "-d", "@1200000000", "missing"]),
+  case(&["-c", "-h", "-d", "@1200000000", "missing"]),
+  case(&["-h", "-r", "link", "file"]),
+  // Time zones.
+  zoned("America/New_York", &["-d", "2024-07-01 12:00", "new"]),
+  zoned("America/New_York", &["-d", "2024-11-03 01:30", "new"]),
+  zoned("America/New_York", &["-t", "202411030130", "new"]),
+  zoned("Europe/Berlin", &["-d", "2024-01-01 12:00 UTC", "new"]),
+  zoned(
+    "Europe/Berlin",
+    &["-d", "TZ=\"UTC\" 2024-01-01 12:00", "new"],
+  ),
+  zoned("Nowhere/Special", &["-d", "2024-01-01 12:00", "new"]),
+];
+
+/// The places rtouch differs from GNU on purpose.
+const DIVERGENCES: &[Divergence] = &[
+  Divergence {
+    case: case(&["-a", "-m", "-d", "@1200000000", "file"]),
+    reason: "-a and -m conflict, rather than both meaning the default",
+  },
+  Divergence {
+    case: case(&["--bogus", "file"]),
+    reason: "usage errors are reported by clap, and exit with status 2",
+  },
+  Divergence {
+    case: case(&["-t", "202001010000", "-d", "@0", "file"]),
+    reason: "-t conflicts with -d as a usage error, exiting with status 2",
+  },
+  Divergence {
+    case: case(&["-d", "2020-01-0112:00:00+0000", "file"]),
+    reason: "an ISO 8601 date and time may be run together",
+  },
+  Divergence {
+    case: case(&["-d", "@1200000000", "é/new"]),
+    reason: "printable characters are never escaped in diagnostics, as GNU \
+             only does in a UTF-8 locale",
+  },
+  Divergence {
+    case: zoned("America/New_York", &["-d", "2024-03-10 02:30", "new"]),
+    reason: "a local time skipped by daylight saving time is explained",
+  },
+  Divergence {
+    case: zoned("America/New_York", &["-t", "202403100230", "new"]),
+    reason: "a local time skipped by daylight saving time is explained",
+  },
+];
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Find GNU touch.
+///
+/// ### Returns:
+/// * `Option<PathBuf>` - The program, or `None` if GNU touch is not
+///   installed.
+fn gnu_touch() -> Option<PathBuf> {
+  let program = env::var_os("RTOUCH_GNU_TOUCH")
+    .map_or(PathBuf::from("touch"), PathBuf::from);
+  let output = Command::new(&program).arg("--version").output().ok()?;
+  let version = String::from_utf8_lossy(&output.stdout);
+  if !version.contains("GNU coreutils") {
+    return None;
+  }
+  Some(program)
+}
+
+/// ## Make a scratch directory with a file of each kind.
+///
+/// * `file` - Accessed at 1000000000.25 and modified at 1100000000.75.
+/// * `dir` - A directory, with the same times.
+/// * `readonly` - A file with mode 0444, with the same times.
+/// * `link` - A symbolic link to `file`.
+/// * `dangling` - A symbolic link to `nowhere`, which does not exist.
+///
+/// ### Returns:
+/// * `TempDir` - The directory, removed when dropped.
+fn scratch() -> TempDir {
+  let dir = TempDir::new().unwrap();
+  let times = FileTimes::new()
+    .set_accessed(at(1_000_000_000, 250_000_000))
+    .set_modified(at(1_100_000_000, 750_000_000));
+  for name in ["file", "readonly"] {
+    File::create(dir.path().join(name))
+      .unwrap()
+      .set_times(times)
+      .unwrap();
+  }
+  fs::set_permissions(
+    dir.path().join("readonly"),
+    Permissions::from_mode(0o444),
+  )
+  .unwrap();
+  fs::create_dir(dir.path().join("dir")).unwrap();
+  File::open(dir.path().join("dir"))
+    .unwrap()
+    .set_times(times)
+    .unwrap();
+  symlink("file", dir.path().join("link")).unwrap();
+  symlink("nowhere", dir.path().join("dangling")).unwrap();
+  dir
+}
+
+/// ## Run a program in a fresh scratch directory.
+///
+/// ### Arguments:
+/// * `program` - The program to run.
+/// * `args` - The arguments.
+/// * `zone` - The time zone to run in, if not UTC.
+///
+/// ### Returns:
+/// * `Outcome` - What the program left behind.
+fn run(program: &Path, args: &[&str], zone: Option<&str>) -> Outcome {
+  let dir = scratch();
+  let output = Command::new(program)
+    .current_dir(dir.path())
+    .env("LC_ALL", "C")
+    .env("TZ", zone.unwrap_or("UTC"))
+    .env_remove("SOURCE_DATE_EPOCH")
+    .args(args)
+    .output()
+    .unwrap();
+  // The diagnostics are the same but for the name of the program.
+  let stderr = String::from_utf8_lossy(&output.stderr)
+    .lines()
+    .map(|line| match line.strip_prefix("touch: ") {
+      Some(rest) => format!("rtouch: {}\n", rest),
+      None => format!("{}\n", line),
+    })
+    .collect();
+  Outcome {
+    status: output.status.code(),
+    stderr,
+    files: files(dir.path()),
+  }
+}
+
+/// ## List the files in a directory, and everything below it.
+///
+/// ### Arguments:
+/// * `dir` - The directory.
+///
+/// ### Returns:
+/// * `Vec<(PathBuf, &str, SystemTime, SystemTime)>` - The path relative to
+///   the directory, type, access time and modification time of each file,
+///   sorted by path.
+fn files(dir: &Path) -> Vec<(PathBuf, &'static str, SystemTime, SystemTime)> {
+  let mut files = Vec::new();
+  let mut pending = vec![dir.to_path_buf()];
+  while let Some(next) = pending.pop() {
+    for entry in fs::read_dir(&next).unwrap() {
+      let path = entry.unwrap().path();
+      let metadata = fs::symlink_metadata(&path).unwrap();
+      let kind = if metadata.is_symlink() {
+        "link"
+      } else if metadata.is_dir() {
+        pending.push(path.clone());
+        "dir"
+      } else {
+        "file"
+      };
+      files.push((
+        path.strip_prefix(dir).unwrap().to_path_buf(),
+        kind,
+        metadata.accessed().unwrap(),
+        metadata.modified().unwrap(),
+      ));
+    }
+  }
+  files.sort();
+  files
+}
+
+/// ## Whether two times are the same.
+///
+/// Times that are close to the current time can only be compared to within
+/// a few seconds, as the two programs run one after the other, and so can any
+/// time in an approximate case. Following a symbolic link sets its access
+/// time to the current time, too.
+///
+/// ### Arguments:
+/// * `a` - One time.
+/// * `b` - The other time.
+/// * `approximate` - Whether the case is approximate.
+///
+/// ### Returns:
+/// * `bool` - Whether the times match.
+fn same_time(a: SystemTime, b: SystemTime, approximate: bool) -> bool {
+  let close = |a: SystemTime, b: SystemTime| {
+    a.duration_since(b)
+      .or_else(|_| b.duration_since(a))
+      .is_ok_and(|difference| difference < Duration::from_secs(5))
+  };
+  let now = SystemTime::now();
+  a == b || close(a, b) && (approximate || close(a, now))
+}
+
+/// ## Whether two outcomes are the same.
+///
+/// ### Arguments:
+/// * `gnu` - What GNU touch did.
+/// * `rtouch` - What rtouch did.
+/// * `approximate` - Whether times need only be close.
+///
+/// ### Returns:
+/// * `Result<(), String>` - The first difference, if there is one.
+fn compare(
+  gnu: &Outcome,
+  rtouch: &Outcome,
+  approximate: bool,
+) -> Result<(), String> {
+  if gnu.status != rtouch.status {
+    return Err(format!(
+      "exit status {:?} from GNU, {:?} from rtouch",
+      gnu.status, rtouch.status
+    ));
+  }
+  if gnu.stderr != rtouch.stderr {
+    return Err(format!(
+      "stderr {:?} from GNU, {:?} from rtouch",
+      gnu.stderr, rtouch.stderr
+    ));
+  }
+  let names = |outcome: &Outcome| -> Vec<(PathBuf, &str)> {
+    outcome
+      .files
+      .iter()
+      .map(|(path, kind, _, _)| (path.clone(), *kind))
+      .collect()
+  };
+  if names(gnu) != names(rtouch) {
+    return Err(format!(
+      "files {:?} from GNU, {:?} from rtouch",
+      names(gnu),
+      names(rtouch)
+    ));
+  }
+  for (g, r) in gnu.files.iter().zip(&rtouch.files) {
+    if !same_time(g.2, r.2, approximate) || !same_time(g.3, r.3, approximate) {
+      return Err(format!(
+        "times of {:?} are {:?} from GNU, {:?} from rtouch",
+        g.0,
+        (g.2, g.3),
+        (r.2, r.3)
+      ));
+    }
+  }
+  Ok(())
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn rtouch_matches_gnu_touch() {
+  let Some(gnu) = gnu_touch() else {
+    eprintln!("GNU touch is not installed, skipping");
+    return;
+  };
+  let rtouch = PathBuf::from(env!("CARGO_BIN_EXE_rtouch"));
+  let failures: Vec<String> = CASES
+    .iter()
+    .filter_map(|case| {
+      let expected = run(&gnu, case.args, case.zone);
+      let actual = run(&rtouch, case.args, case.zone);
+      compare(&expected, &actual, case.approximate)
+        .err()
+        .map(|difference| {
+          format!("{:?} in {:?}: {}", case.args, case.zone, difference)
+        })
+    })
+    .collect();
+  assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn known_divergences_still_diverge() {
+  let Some(gnu) = gnu_touch() else {
+    eprintln!("GNU touch is not installed, skipping");
+    return;
+  };
+  let rtouch = PathBuf::from(env!("CARGO_BIN_EXE_rtouch"));
+  let converged: Vec<String> = DIVERGENCES
+    .iter()
+    .filter(|divergence| {
+      let case = &divergence.case;
+      let expected = run(&gnu, case.args, case.zone);
+      let actual = run(&rtouch, case.args, case.zone);
+      compare(&expected, &actual, case.approximate).is_ok()
+    })
+    .map(|divergence| {
+      format!("{:?}: {}", divergence.case.args, divergence.reason)
+    })
+    .collect();
+  assert!(
+    converged.is_empty(),
+    "these now match GNU, and can be removed from DIVERGENCES:\n{}",
+    converged.join("\n")
+  );
+}
@@ -0,0 +1,135 @@
+//! # --every
+//!
+//! Check that heartbeats keep touching their files, and stop when they are
+//! told to, in a temporary directory created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{modified, rtouch, stderr, stdout};
+use std::{
+  path::Path,
+  process::{Child, Command, Output, Stdio},
+  thread,
+  time::{Duration, Instant},
+};
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Start rtouch in a directory, without waiting for it to finish.
+///
+/// ### Arguments:
+/// * `dir` - The directory to run in.
+/// * `args` - The arguments.
+///
+/// ### Returns:
+/// * `Child` - The running rtouch, with its output captured.
+fn spawn(dir: &Path, args: &[&str]) -> Child {
+  common::command(dir, args)
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap()
+}
+
+/// ## Wait for a heartbeat to exit, failing the test if it takes too long.
+///
+/// ### Arguments:
+/// * `child` - The running rtouch.
+///
+/// ### Returns:
+/// * `Output` - What rtouch printed, and its exit status.
+fn finish(mut child: Child) -> Output {
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while child.try_wait().unwrap().is_none() {
+    if Instant::now() > deadline {
+      child.kill().unwrap();
+      panic!("rtouch did not stop");
+    }
+    thread::sleep(Duration::from_millis(20));
+  }
+  child.wait_with_output().unwrap()
+}
+
+/// ## Wait for a heartbeat to create a file, failing the test if it does not.
+fn wait_for(path: &Path) {
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while !path.exists() {
+    assert!(
+      Instant::now() < deadline,
+      "{} was not created",
+      path.display()
+    );
+    thread::sleep(Duration::from_millis(20));
+  }
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn files_are_touched_every_interval_until_the_time_is_up() {
+  let dir = TempDir::new().unwrap();
+  let args = ["-v", "--every", "200ms", "--for", "1s", "beat"];
+  let start = Instant::now();
+  let output = finish(spawn(dir.path(), &args));
+  assert!(output.status.success());
+  assert!(start.elapsed() >= Duration::from_secs(1));
+  let stdout = stdout(&output);
+  let rounds: Vec<_> = stdout.lines().collect();
+  // Rounds at 0, 200, 400, 600 and 800ms, though a slow one may be missed.
+  assert!((3..=5).contains(&rounds.len()), "{}", stdout);
+  assert!(rounds[0].starts_with("rtouch: created 'beat'"));
+  assert!(rounds[1..]
+    .iter()
+    .all(|round| round.starts_with("rtouch: updated 'beat'")));
+}
+
+#[test]
+fn each_round_sets_the_current_time() {
+  let dir = TempDir::new().unwrap();
+  let child = spawn(dir.path(), &["--every", "100ms", "--for", "2s", "beat"]);
+  wait_for(&dir.path().join("beat"));
+  let first = modified(&dir.path().join("beat"));
+  thread::sleep(Duration::from_millis(500));
+  assert!(modified(&dir.path().join("beat")) > first);
+  assert!(finish(child).status.success());
+}
+
+#[test]
+fn signals_stop_a_heartbeat_cleanly() {
+  for signal in [libc::SIGTERM, libc::SIGINT] {
+    let dir = TempDir::new().unwrap();
+    let child = spawn(dir.path(), &["--every", "100ms", "beat"]);
+    // The handlers are installed before the first round creates the file, so
+    // until then the signal would kill rtouch instead.
+    wait_for(&dir.path().join("beat"));
+    // SAFETY: the child has not been waited for, so its pid is still its own.
+    assert_eq!(unsafe { libc::kill(child.id() as libc::pid_t, signal) }, 0);
+    let output = finish(child);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stderr(&output), "");
+  }
+}
+
+#[test]
+fn a_heartbeat_stops_when_the_watched_process_exits() {
+  let dir = TempDir::new().unwrap();
+  let mut worker = Command::new("sleep").arg("0.5").spawn().unwrap();
+  let pid = worker.id().to_string();
+  let child = spawn(dir.path(), &["--every", "100ms", "--pid", &pid, "beat"]);
+  // The worker is reaped, so that its pid no longer exists.
+  worker.wait().unwrap();
+  assert!(finish(child).status.success());
+}
+
+#[test]
+fn intervals_must_be_positive() {
+  let dir = TempDir::new().unwrap();
+  for interval in ["0s", "-1s", "soon"] {
+    let args = ["--every", interval, "beat"];
+    let output = rtouch(dir.path(), &args);
+    assert_eq!(output.status.code(), Some(2), "{}", interval);
+  }
+  assert!(!dir.path().join("beat").exists());
+}
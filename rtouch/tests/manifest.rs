@@ -0,0 +1,153 @@
+//! # --save-times and --restore-times
+//!
+//! Check that times saved to a manifest are put back exactly, and that files
+//! which vanished or changed since are reported, in a temporary directory
+//! created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{at, command, rtouch, stderr, succeed, times};
+use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt};
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Create files with awkward times and names, and save their times.
+///
+/// `a` has nanoseconds, `b` is from before the epoch, and the third file,
+/// from 900000000, has a name holding a tab, a newline and a byte that is
+/// not valid UTF-8. Their
+/// times are saved to `manifest`, and then all set to 1200000000.
+///
+/// ### Returns:
+/// * `TempDir` - The directory holding the files, removed when dropped.
+fn saved() -> TempDir {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000.123456789", "a"]);
+  succeed(dir.path(), &["-d", "@-100.25", "b"]);
+  fs::write(dir.path().join(awkward()), "contents").unwrap();
+  let output = command(dir.path(), &["-d", "@900000000"])
+    .arg(awkward())
+    .output()
+    .unwrap();
+  assert!(output.status.success());
+  succeed(dir.path(), &["-m", "-d", "@1100000000", "a"]);
+  let output = command(dir.path(), &["--save-times", "manifest", "a", "b"])
+    .arg(awkward())
+    .output()
+    .unwrap();
+  assert_eq!(stderr(&output), "");
+  assert!(output.status.success());
+  let output = command(dir.path(), &["-d", "@1200000000", "a", "b"])
+    .arg(awkward())
+    .output()
+    .unwrap();
+  assert!(output.status.success());
+  dir
+}
+
+/// ## The name of a file that is hard to write to a manifest.
+fn awkward() -> &'static OsStr {
+  OsStr::from_bytes(b"tab\tnew\nline\xff")
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn saved_times_are_restored_exactly() {
+  let dir = saved();
+  let output = rtouch(dir.path(), &["--restore-times", "manifest"]);
+  assert_eq!(stderr(&output), "");
+  assert!(output.status.success());
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 123_456_789), at(1_100_000_000, 0))
+  );
+  assert_eq!(
+    times(&dir.path().join("b")),
+    (at(-101, 750_000_000), at(-101, 750_000_000))
+  );
+  assert_eq!(
+    times(&dir.path().join(awkward())),
+    (at(900_000_000, 0), at(900_000_000, 0))
+  );
+}
+
+#[test]
+fn saving_leaves_times_alone_and_creates_nothing() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "a"]);
+  let args = ["--save-times", "manifest", "a", "missing"];
+  let output = rtouch(dir.path(), &args);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: failed to get attributes of 'missing': No such file or directory\n"
+  );
+  assert!(!dir.path().join("missing").exists());
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+}
+
+#[test]
+fn files_whose_size_changed_are_left_alone() {
+  let dir = saved();
+  fs::write(dir.path().join("a"), "rewritten").unwrap();
+  succeed(dir.path(), &["-d", "@1200000000", "a"]);
+  let output = rtouch(dir.path(), &["--restore-times", "manifest"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot restore times of 'a': size changed from 0 to 9 bytes\n"
+  );
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_200_000_000, 0), at(1_200_000_000, 0))
+  );
+  // The other files are still restored.
+  assert_eq!(times(&dir.path().join("b")).1, at(-101, 750_000_000));
+}
+
+#[test]
+fn vanished_files_are_reported_and_not_created() {
+  let dir = saved();
+  fs::remove_file(dir.path().join("b")).unwrap();
+  let output = rtouch(dir.path(), &["--restore-times", "manifest"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot restore times of 'b': No such file or directory\n"
+  );
+  assert!(!dir.path().join("b").exists());
+  assert_eq!(times(&dir.path().join("a")).1, at(1_100_000_000, 0));
+}
+
+#[test]
+fn invalid_lines_are_reported_and_skipped() {
+  let dir = saved();
+  let manifest = dir.path().join("manifest");
+  let mut contents = fs::read(&manifest).unwrap();
+  contents.extend_from_slice(b"not a manifest line\n");
+  fs::write(&manifest, contents).unwrap();
+  let output = rtouch(dir.path(), &["--restore-times", "manifest"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: 'manifest':5: invalid manifest line\n"
+  );
+  assert_eq!(times(&dir.path().join("a")).1, at(1_100_000_000, 0));
+}
+
+#[test]
+fn missing_manifests_are_an_error() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--restore-times", "manifest"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot open 'manifest' for reading: No such file or directory\n"
+  );
+}
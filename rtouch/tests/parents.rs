@@ -0,0 +1,143 @@
+//! # -p
+//!
+//! Check that `-p` creates the missing parents of a file, with the modes
+//! given by `--dir-mode`, and reports each one, in a temporary directory
+//! created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{
+  at, mode, rtouch, rtouch_as_nobody, stderr, stdout, succeed, times,
+};
+use std::{fs, os::unix::fs::PermissionsExt};
+use tempfile::TempDir;
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn creates_missing_parents() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-p", "-d", "@0", "a/b/c/f"]);
+  assert!(dir.path().join("a/b/c").is_dir());
+  assert_eq!(times(&dir.path().join("a/b/c/f")), (at(0, 0), at(0, 0)));
+  // Existing parents are left as they are.
+  succeed(dir.path(), &["-p", "-d", "@0", "a/b/g"]);
+  assert_eq!(fs::read_dir(dir.path().join("a/b")).unwrap().count(), 2);
+}
+
+#[test]
+fn parents_are_only_created_with_p() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["a/f"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'a/f': No such file or directory\n"
+  );
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn files_in_the_way_are_reported() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["f"]);
+  let output = rtouch(dir.path(), &["-p", "f/g"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'f/g': Not a directory\n"
+  );
+}
+
+#[test]
+fn dir_mode_is_given_to_each_created_directory() {
+  let dir = TempDir::new().unwrap();
+  fs::create_dir(dir.path().join("a")).unwrap();
+  fs::set_permissions(dir.path().join("a"), fs::Permissions::from_mode(0o755))
+    .unwrap();
+  succeed(dir.path(), &["-p", "--dir-mode", "0750", "a/b/c/f"]);
+  assert_eq!(mode(&dir.path().join("a")), 0o755);
+  assert_eq!(mode(&dir.path().join("a/b")), 0o750);
+  assert_eq!(mode(&dir.path().join("a/b/c")), 0o750);
+  succeed(dir.path(), &["-p", "--dir-mode", "u=rwx,go=", "x/f"]);
+  assert_eq!(mode(&dir.path().join("x")), 0o700);
+}
+
+#[test]
+fn dir_mode_is_given_once_the_file_exists() {
+  let dir = TempDir::new().unwrap();
+  let args = ["-p", "--dir-mode", "0500", "a/b/f"];
+  // Root may write to any directory, so only another user can tell.
+  let Some(output) = rtouch_as_nobody(dir.path(), &args) else {
+    return;
+  };
+  assert_eq!(stderr(&output), "");
+  assert!(output.status.success());
+  assert!(dir.path().join("a/b/f").exists());
+  assert_eq!(mode(&dir.path().join("a")), 0o500);
+  assert_eq!(mode(&dir.path().join("a/b")), 0o500);
+}
+
+#[test]
+fn names_with_a_trailing_slash_create_no_parents() {
+  let dir = TempDir::new().unwrap();
+  for args in [&["-p", "d/e/"][..], &["-n", "-p", "d/e/"]] {
+    let output = rtouch(dir.path(), args);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+      stderr(&output),
+      "rtouch: cannot touch 'd/e/': No such file or directory\n"
+    );
+  }
+  assert!(!dir.path().join("d").exists());
+  succeed(dir.path(), &["d"]);
+  for args in [&["-p", "d/"][..], &["-n", "-p", "d/"]] {
+    let output = rtouch(dir.path(), args);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+      stderr(&output),
+      "rtouch: cannot touch 'd/': Not a directory\n"
+    );
+  }
+}
+
+#[test]
+fn dir_mode_needs_p() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--dir-mode", "0700", "a/f"]);
+  assert_eq!(output.status.code(), Some(2));
+  let output = rtouch(dir.path(), &["-p", "--dir-mode", "rw", "a/f"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn verbose_lists_created_directories() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-p", "a/f"]);
+  let output = rtouch(dir.path(), &["-v", "-p", "-d", "@0", "a/b/c/f"]);
+  assert!(output.status.success());
+  assert_eq!(
+    stdout(&output),
+    "rtouch: created directory 'a/b'\n\
+     rtouch: created directory 'a/b/c'\n\
+     rtouch: created 'a/b/c/f' (access 1970-01-01 00:00:00.000000000 +0000, \
+     modify 1970-01-01 00:00:00.000000000 +0000)\n"
+  );
+}
+
+#[test]
+fn dry_runs_only_say_what_would_be_created() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-n", "-v", "-p", "-d", "@0", "a/b/f"]);
+  assert!(output.status.success());
+  assert_eq!(
+    stdout(&output),
+    "rtouch: would create directory 'a'\n\
+     rtouch: would create directory 'a/b'\n\
+     rtouch: would create 'a/b/f' (access 1970-01-01 00:00:00.000000000 +0000, \
+     modify 1970-01-01 00:00:00.000000000 +0000)\n"
+  );
+  assert!(!dir.path().join("a").exists());
+}
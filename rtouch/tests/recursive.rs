@@ -0,0 +1,199 @@
+//! # -R
+//!
+//! Check which entries `-R` updates, and how `--include`, `--exclude`,
+//! `--max-depth`, `--no-dirs` and `--follow-symlinks` narrow or widen the
+//! walk, in a temporary directory created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{at, modified, rtouch, stderr, succeed};
+use std::{fs, os::unix::fs::symlink};
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Create a tree to walk, with every time at 1000000000.
+///
+/// The tree is:
+///
+/// ```text
+/// tree/a.c
+/// tree/a.o
+/// tree/src/b.c
+/// tree/src/deep/c.c
+/// tree/.git/HEAD
+/// tree/link -> ../outside
+/// outside/d.c
+/// ```
+///
+/// ### Returns:
+/// * `TempDir` - The directory holding the tree, removed when dropped.
+fn tree() -> TempDir {
+  let dir = TempDir::new().unwrap();
+  let root = dir.path();
+  for path in ["tree/src/deep", "tree/.git", "outside"] {
+    fs::create_dir_all(root.join(path)).unwrap();
+  }
+  for path in ["a.c", "a.o", "src/b.c", "src/deep/c.c", ".git/HEAD"] {
+    fs::write(root.join("tree").join(path), "").unwrap();
+  }
+  fs::write(root.join("outside/d.c"), "").unwrap();
+  symlink("../outside", root.join("tree/link")).unwrap();
+  let args = ["-d", "@1000000000", "tree", "tree/src", "tree/src/deep"];
+  succeed(root, &args);
+  succeed(root, &["-d", "@1000000000", "tree/.git", "outside"]);
+  for path in ["a.c", "a.o", "src/b.c", "src/deep/c.c", ".git/HEAD"] {
+    let path = format!("tree/{}", path);
+    succeed(root, &["-d", "@1000000000", &path]);
+  }
+  succeed(root, &["-d", "@1000000000", "outside/d.c"]);
+  dir
+}
+
+/// ## Run rtouch with `-d @0` and list the entries it updated.
+///
+/// ### Arguments:
+/// * `args` - The arguments, without `-d @0`.
+///
+/// ### Returns:
+/// * `Vec<String>` - The paths below the temporary directory whose
+///   modification time, following symbolic links, is now 0.
+fn updated(args: &[&str]) -> Vec<String> {
+  let dir = tree();
+  let mut all = vec!["-d", "@0"];
+  all.extend(args);
+  succeed(dir.path(), &all);
+  WalkDir::new(dir.path())
+    .min_depth(1)
+    .sort_by_file_name()
+    .into_iter()
+    .map(|entry| entry.unwrap().into_path())
+    .filter(|path| modified(path) == at(0, 0))
+    .map(|path| {
+      let relative = path.strip_prefix(dir.path()).unwrap();
+      relative.to_string_lossy().into_owned()
+    })
+    .collect()
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn updates_everything_below_the_root() {
+  assert_eq!(
+    updated(&["-R", "tree"]),
+    [
+      // The link is not followed, but its target is updated like any other
+      // operand.
+      "outside",
+      "tree",
+      "tree/.git",
+      "tree/.git/HEAD",
+      "tree/a.c",
+      "tree/a.o",
+      "tree/link",
+      "tree/src",
+      "tree/src/b.c",
+      "tree/src/deep",
+      "tree/src/deep/c.c",
+    ]
+  );
+}
+
+#[test]
+fn include_only_applies_to_files() {
+  assert_eq!(
+    updated(&["-R", "--no-dirs", "--include", "*.c", "tree"]),
+    ["tree/a.c", "tree/src/b.c", "tree/src/deep/c.c"]
+  );
+  assert_eq!(
+    updated(&["-R", "--include", "src/*.c", "tree"]),
+    [
+      "tree",
+      "tree/.git",
+      "tree/src",
+      "tree/src/b.c",
+      "tree/src/deep"
+    ]
+  );
+}
+
+#[test]
+fn exclude_skips_whole_directories() {
+  assert_eq!(
+    updated(&["-R", "--exclude", ".git", "--exclude", "*.o", "tree"]),
+    [
+      "outside",
+      "tree",
+      "tree/a.c",
+      "tree/link",
+      "tree/src",
+      "tree/src/b.c",
+      "tree/src/deep",
+      "tree/src/deep/c.c",
+    ]
+  );
+  assert_eq!(
+    // Paths are relative to the directory being walked.
+    updated(&["-R", "--no-dirs", "--exclude", "deep/*", "tree/src"]),
+    ["tree/src/b.c"]
+  );
+}
+
+#[test]
+fn max_depth_limits_how_far_down_to_go() {
+  assert_eq!(updated(&["-R", "--max-depth", "0", "tree"]), ["tree"]);
+  assert_eq!(
+    updated(&["-R", "--no-dirs", "--max-depth", "1", "tree"]),
+    ["outside", "tree/a.c", "tree/a.o", "tree/link"]
+  );
+}
+
+#[test]
+fn follow_symlinks_descends_into_linked_directories() {
+  assert_eq!(
+    updated(&[
+      "-R",
+      "--follow-symlinks",
+      "--no-dirs",
+      "--include",
+      "*.c",
+      "tree"
+    ]),
+    [
+      "outside/d.c",
+      "tree/a.c",
+      "tree/src/b.c",
+      "tree/src/deep/c.c",
+    ]
+  );
+}
+
+#[test]
+fn files_are_updated_as_they_are() {
+  assert_eq!(
+    updated(&["-R", "--exclude", "*.c", "tree/a.c"]),
+    ["tree/a.c"]
+  );
+}
+
+#[test]
+fn walk_options_need_recursion() {
+  let dir = tree();
+  let options = [
+    "--include=*.c",
+    "--exclude=*.c",
+    "--max-depth=1",
+    "--no-dirs",
+    "--follow-symlinks",
+  ];
+  for option in options {
+    let output = rtouch(dir.path(), &[option, "tree"]);
+    assert_eq!(output.status.code(), Some(2));
+  }
+  let output = rtouch(dir.path(), &["-R", "-c", "missing"]);
+  assert!(output.status.success());
+  assert_eq!(stderr(&output), "");
+}
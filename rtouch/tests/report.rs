@@ -0,0 +1,163 @@
+//! # Reports
+//!
+//! Check what `-v`, `-n` and `--json` say about each file, and that a dry run
+//! changes nothing, in a temporary directory created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{at, modified, rtouch, stderr, stdout, succeed, times};
+use serde_json::Value;
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Parse each line rtouch printed as JSON.
+fn json_lines(text: &str) -> Vec<Value> {
+  text
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .collect()
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn verbose_describes_each_file() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "old"]);
+  let args = ["-v", "-c", "-d", "2024-05-01 12:00:00.5", "old", "missing"];
+  let output = rtouch(dir.path(), &args);
+  assert!(output.status.success());
+  assert_eq!(
+    stdout(&output),
+    "rtouch: updated 'old' (access 2024-05-01 12:00:00.500000000 +0000, \
+     modify 2024-05-01 12:00:00.500000000 +0000)\n\
+     rtouch: skipped 'missing'\n"
+  );
+  let output = rtouch(dir.path(), &["-v", "-m", "-d", "@0", "old"]);
+  assert_eq!(
+    stdout(&output),
+    "rtouch: updated 'old' (modify 1970-01-01 00:00:00.000000000 +0000)\n"
+  );
+}
+
+#[test]
+fn verbose_failures_go_to_standard_error() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-v", "-d", "@0", "nodir/a", "b"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stdout(&output),
+    "rtouch: created 'b' (access 1970-01-01 00:00:00.000000000 +0000, \
+     modify 1970-01-01 00:00:00.000000000 +0000)\n"
+  );
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'nodir/a': No such file or directory\n"
+  );
+}
+
+#[test]
+fn dry_runs_change_nothing() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "old"]);
+  let output = rtouch(dir.path(), &["-n", "-v", "-d", "@0", "old", "new"]);
+  assert!(output.status.success());
+  assert_eq!(
+    stdout(&output),
+    "rtouch: would update 'old' (access 1970-01-01 00:00:00.000000000 +0000, \
+     modify 1970-01-01 00:00:00.000000000 +0000)\n\
+     rtouch: would create 'new' (access 1970-01-01 00:00:00.000000000 +0000, \
+     modify 1970-01-01 00:00:00.000000000 +0000)\n"
+  );
+  assert_eq!(
+    times(&dir.path().join("old")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+  assert!(!dir.path().join("new").exists());
+}
+
+#[test]
+fn dry_runs_still_report_failures() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-n", "nodir/a"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'nodir/a': No such file or directory\n"
+  );
+  assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn json_reports_old_and_new_times() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "old"]);
+  let args = ["--json", "-m", "-d", "@0.25", "old", "nodir/a"];
+  let output = rtouch(dir.path(), &args);
+  assert_eq!(output.status.code(), Some(1));
+  let lines = json_lines(&stdout(&output));
+  assert_eq!(
+    lines[0],
+    serde_json::json!({
+      "file": "old",
+      "action": "updated",
+      "dry_run": false,
+      "old": {
+        "atime": "2001-09-09T01:46:40.000000000Z",
+        "mtime": "2001-09-09T01:46:40.000000000Z",
+      },
+      "new": {
+        "atime": "2001-09-09T01:46:40.000000000Z",
+        "mtime": "1970-01-01T00:00:00.250000000Z",
+      },
+      "error": null,
+    })
+  );
+  assert_eq!(lines[1]["file"], "nodir/a");
+  assert_eq!(lines[1]["action"], "failed");
+  assert_eq!(lines[1]["error"]["kind"], "NotFound");
+  assert_eq!(
+    lines[1]["error"]["message"],
+    "cannot touch 'nodir/a': No such file or directory"
+  );
+  assert_eq!(lines.len(), 2);
+}
+
+#[test]
+fn json_reports_the_times_created_files_really_have() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--json", "-a", "-d", "@0", "new"]);
+  assert!(output.status.success());
+  let lines = json_lines(&stdout(&output));
+  assert_eq!(lines[0]["action"], "created");
+  assert_eq!(lines[0]["old"]["mtime"], Value::Null);
+  assert_eq!(lines[0]["new"]["atime"], "1970-01-01T00:00:00.000000000Z");
+  // The modification time is the time the file was created.
+  let mtime = lines[0]["new"]["mtime"].as_str().unwrap();
+  let created = chrono::DateTime::parse_from_rfc3339(mtime).unwrap();
+  assert_eq!(
+    std::time::SystemTime::from(created),
+    modified(&dir.path().join("new"))
+  );
+}
+
+#[test]
+fn json_marks_dry_runs() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--json", "-n", "-d", "@0", "new"]);
+  assert!(output.status.success());
+  let lines = json_lines(&stdout(&output));
+  assert_eq!(lines[0]["action"], "created");
+  assert_eq!(lines[0]["dry_run"], true);
+  assert!(!dir.path().join("new").exists());
+}
+
+#[test]
+fn json_and_verbose_conflict() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--json", "-v", "a"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(!dir.path().join("a").exists());
+}
@@ -0,0 +1,131 @@
+//! # SOURCE_DATE_EPOCH and --clamp
+//!
+//! Check that `SOURCE_DATE_EPOCH` replaces the current time, and that
+//! `--clamp` only ever lowers times, in a temporary directory created for
+//! each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{at, command, modified, rtouch, stderr, stdout, succeed, times};
+use std::{path::Path, process::Output};
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## Run rtouch with `SOURCE_DATE_EPOCH` set.
+///
+/// ### Arguments:
+/// * `dir` - The directory to run in.
+/// * `epoch` - The value of `SOURCE_DATE_EPOCH`.
+/// * `args` - The arguments.
+///
+/// ### Returns:
+/// * `Output` - What rtouch printed, and its exit status.
+fn rtouch_at(dir: &Path, epoch: &str, args: &[&str]) -> Output {
+  command(dir, args)
+    .env("SOURCE_DATE_EPOCH", epoch)
+    .output()
+    .unwrap()
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn source_date_epoch_replaces_the_current_time() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch_at(dir.path(), "1700000000", &["a", "b"]);
+  assert!(output.status.success());
+  for name in ["a", "b"] {
+    assert_eq!(
+      times(&dir.path().join(name)),
+      (at(1_700_000_000, 0), at(1_700_000_000, 0))
+    );
+  }
+}
+
+#[test]
+fn explicit_times_take_precedence() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@5", "ref"]);
+  for args in [
+    &["-d", "@0", "a"][..],
+    &["-t", "197001010000", "a"],
+    &["-r", "ref", "-d", "-5 seconds", "a"],
+  ] {
+    let output = rtouch_at(dir.path(), "1700000000", args);
+    assert!(output.status.success());
+    assert_eq!(modified(&dir.path().join("a")), at(0, 0));
+  }
+}
+
+#[test]
+fn empty_source_date_epoch_is_ignored() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch_at(dir.path(), "", &["a"]);
+  assert!(output.status.success());
+  assert!(modified(&dir.path().join("a")) > at(1_700_000_000, 0));
+}
+
+#[test]
+fn invalid_source_date_epoch_is_an_error() {
+  let dir = TempDir::new().unwrap();
+  for epoch in ["abc", "-5", "1.5", " 1"] {
+    let output = rtouch_at(dir.path(), epoch, &["a"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+      stderr(&output),
+      format!("rtouch: invalid SOURCE_DATE_EPOCH '{}'\n", epoch)
+    );
+  }
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn source_date_epoch_is_only_read_when_used() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "a"]);
+  let output = rtouch_at(dir.path(), "abc", &["--shift", "1h", "a"]);
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("a")), at(1_000_003_600, 0));
+  let output = rtouch_at(dir.path(), "abc", &["--save-times", "manifest", "a"]);
+  assert!(output.status.success());
+  succeed(dir.path(), &["-d", "@1200000000", "a"]);
+  let output = rtouch_at(dir.path(), "abc", &["--restore-times", "manifest"]);
+  assert!(output.status.success());
+  assert_eq!(modified(&dir.path().join("a")), at(1_000_003_600, 0));
+}
+
+#[test]
+fn clamp_only_lowers_times() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000", "a"]);
+  succeed(dir.path(), &["-m", "-d", "@3000", "a"]);
+  succeed(dir.path(), &["-d", "@1000", "old"]);
+  let output = rtouch_at(dir.path(), "2000", &["-v", "--clamp", "a", "old"]);
+  assert!(output.status.success());
+  assert_eq!(
+    stdout(&output),
+    "rtouch: updated 'a' (modify 1970-01-01 00:33:20.000000000 +0000)\n\
+     rtouch: skipped 'old'\n"
+  );
+  assert_eq!(times(&dir.path().join("a")), (at(1000, 0), at(2000, 0)));
+  assert_eq!(times(&dir.path().join("old")), (at(1000, 0), at(1000, 0)));
+}
+
+#[test]
+fn clamp_creates_missing_files() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["--clamp", "-d", "@2000", "new"]);
+  assert_eq!(times(&dir.path().join("new")), (at(2000, 0), at(2000, 0)));
+  succeed(dir.path(), &["--clamp", "-c", "-d", "@2000", "missing"]);
+  assert!(!dir.path().join("missing").exists());
+}
+
+#[test]
+fn clamp_and_shift_conflict() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--clamp", "--shift", "1h", "a"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(!dir.path().join("a").exists());
+}
@@ -0,0 +1,93 @@
+//! # --shift
+//!
+//! Check that `--shift` moves the times a file already has, and never creates
+//! one, in a temporary directory created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{at, rtouch, stderr, succeed, times};
+use tempfile::TempDir;
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn shifts_both_times_forward() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000.25", "a"]);
+  succeed(dir.path(), &["-m", "-d", "@1100000000", "a"]);
+  succeed(dir.path(), &["--shift", "1h1.5s", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (
+      at(1_000_003_601, 750_000_000),
+      at(1_100_003_601, 500_000_000)
+    )
+  );
+}
+
+#[test]
+fn shifts_back_one_time() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "a"]);
+  succeed(dir.path(), &["-a", "--shift=-1d30m", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(999_911_800, 0), at(1_000_000_000, 0))
+  );
+  succeed(dir.path(), &["-m", "--shift", "+2w", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(999_911_800, 0), at(1_001_209_600, 0))
+  );
+}
+
+#[test]
+fn missing_files_are_an_error() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--shift", "1h", "gone"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: failed to get attributes of 'gone': No such file or directory\n"
+  );
+  assert!(!dir.path().join("gone").exists());
+}
+
+#[test]
+fn missing_files_are_skipped_with_no_create() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-c", "--shift", "1h", "gone"]);
+  assert!(output.status.success());
+  assert_eq!(stderr(&output), "");
+  assert!(!dir.path().join("gone").exists());
+}
+
+#[test]
+fn times_out_of_range_are_an_error() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "@0", "a"]);
+  for shift in ["100000000w", "-100000000w"] {
+    let output =
+      rtouch(dir.path(), &["-v", &format!("--shift={}", shift), "a"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+      stderr(&output),
+      "rtouch: cannot shift times of 'a': time out of range\n"
+    );
+  }
+  assert_eq!(times(&dir.path().join("a")), (at(0, 0), at(0, 0)));
+}
+
+#[test]
+fn bad_shifts_are_usage_errors() {
+  let dir = TempDir::new().unwrap();
+  for args in [
+    &["--shift", "1x", "a"][..],
+    &["-d", "@0", "--shift", "1h", "a"],
+  ] {
+    let output = rtouch(dir.path(), args);
+    assert_eq!(output.status.code(), Some(2));
+  }
+  assert!(!dir.path().join("a").exists());
+}
@@ -0,0 +1,597 @@
+//! # Touching files
+//!
+//! Check the times rtouch sets for each of the options it shares with GNU
+//! `touch`, the modes of the files it creates, and how it reports failures,
+//! by running it in a temporary directory created for each test.
+// Modules. -------------------------------------------------------------------
+mod common;
+
+// Imports. -------------------------------------------------------------------
+use common::{
+  at, mode, modified, rtouch, rtouch_as_nobody, stderr, stdout, succeed, times,
+};
+use std::{
+  fs::{self, Permissions},
+  os::unix::fs::{symlink, PermissionsExt},
+  path::Path,
+  time::SystemTime,
+};
+use tempfile::TempDir;
+
+// Helpers. -------------------------------------------------------------------
+
+/// ## The access and modification times of a symbolic link itself.
+fn link_times(path: &Path) -> (SystemTime, SystemTime) {
+  let metadata = fs::symlink_metadata(path).unwrap();
+  (metadata.accessed().unwrap(), metadata.modified().unwrap())
+}
+
+/// ## Create a file with the given access and modification times.
+fn file_with_times(dir: &Path, name: &str, accessed: &str, modified: &str) {
+  succeed(dir, &["-a", "-d", accessed, name]);
+  succeed(dir, &["-m", "-d", modified, name]);
+}
+
+// Tests. ---------------------------------------------------------------------
+
+#[test]
+fn missing_files_are_created_with_the_current_time() {
+  let dir = TempDir::new().unwrap();
+  let before = SystemTime::now();
+  succeed(dir.path(), &["a"]);
+  let after = SystemTime::now();
+  let file = dir.path().join("a");
+  assert_eq!(fs::metadata(&file).unwrap().len(), 0);
+  let (accessed, modified) = times(&file);
+  assert_eq!(accessed, modified);
+  assert!(before <= modified && modified <= after);
+}
+
+#[test]
+fn existing_files_are_not_truncated() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("a"), "contents").unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "a"]);
+  // Reading the file may update its access time, so it is read last.
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+  assert_eq!(fs::read(dir.path().join("a")).unwrap(), b"contents");
+}
+
+#[test]
+fn dates_keep_nanoseconds() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "2024-05-01 12:00:00.123456789", "a"]);
+  let time = at(1_714_564_800, 123_456_789);
+  assert_eq!(times(&dir.path().join("a")), (time, time));
+}
+
+#[test]
+fn dates_may_be_before_the_epoch() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-d", "1960-01-01 00:00:00.5", "a"]);
+  let time = at(-315_619_200, 500_000_000);
+  assert_eq!(times(&dir.path().join("a")), (time, time));
+}
+
+#[test]
+fn access_only_leaves_the_modification_time_alone() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "a", "@1000000000", "@1100000000");
+  succeed(dir.path(), &["-a", "-d", "@1200000000", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_200_000_000, 0), at(1_100_000_000, 0))
+  );
+}
+
+#[test]
+fn modification_only_leaves_the_access_time_alone() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "a", "@1000000000", "@1100000000");
+  succeed(dir.path(), &["-m", "-d", "@1200000000", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_200_000_000, 0))
+  );
+}
+
+#[test]
+fn access_only_and_modification_only_conflict() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "a", "@1000000000", "@1100000000");
+  let output = rtouch(dir.path(), &["-a", "-m", "-d", "@1200000000", "a"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(stderr(&output)
+    .starts_with("error: the argument '-a' cannot be used with '-m'"));
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_100_000_000, 0))
+  );
+}
+
+#[test]
+fn time_words_stand_for_access_or_modification_only() {
+  let cases = [
+    ("access", (1_200_000_000, 1_100_000_000)),
+    ("atime", (1_200_000_000, 1_100_000_000)),
+    ("use", (1_200_000_000, 1_100_000_000)),
+    ("modify", (1_000_000_000, 1_200_000_000)),
+    ("mtime", (1_000_000_000, 1_200_000_000)),
+  ];
+  for (word, (accessed, modified)) in cases {
+    let dir = TempDir::new().unwrap();
+    file_with_times(dir.path(), "a", "@1000000000", "@1100000000");
+    let time = format!("--time={}", word);
+    succeed(dir.path(), &[&time, "-d", "@1200000000", "a"]);
+    assert_eq!(
+      times(&dir.path().join("a")),
+      (at(accessed, 0), at(modified, 0)),
+      "--time={}",
+      word
+    );
+  }
+}
+
+#[test]
+fn time_words_conflict_with_the_other_time() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "a", "@1000000000", "@1100000000");
+  for (args, option) in [
+    (["--time=access", "-m"], "-m"),
+    (["--time=mtime", "-a"], "-a"),
+  ] {
+    let output = rtouch(dir.path(), &[args[0], args[1], "-d", "@0", "a"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with(&format!(
+      "error: the argument '--time <WORD>' cannot be used with '{}'",
+      option
+    )));
+  }
+  // Saying the same thing twice is not a conflict.
+  succeed(
+    dir.path(),
+    &["--time=atime", "-a", "-d", "@1200000000", "a"],
+  );
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_200_000_000, 0), at(1_100_000_000, 0))
+  );
+}
+
+#[test]
+fn unknown_time_words_are_usage_errors() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["--time=bogus", "a"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(stderr(&output)
+    .starts_with("error: invalid value 'bogus' for '--time <WORD>'"));
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn no_create_skips_missing_files_silently() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-c", "missing"]);
+  assert!(!dir.path().join("missing").exists());
+}
+
+#[test]
+fn no_create_still_updates_existing_files() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("a"), "").unwrap();
+  succeed(dir.path(), &["-c", "-d", "@1000000000", "a", "missing"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+  assert!(!dir.path().join("missing").exists());
+}
+
+#[test]
+fn reference_times_are_copied_exactly() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "ref", "@1000000000.25", "@1100000000.75");
+  succeed(dir.path(), &["-r", "ref", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (
+      at(1_000_000_000, 250_000_000),
+      at(1_100_000_000, 750_000_000)
+    )
+  );
+}
+
+#[test]
+fn reference_with_access_only() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "ref", "@1000000000", "@1100000000");
+  file_with_times(dir.path(), "a", "@1200000000", "@1300000000");
+  succeed(dir.path(), &["-a", "-r", "ref", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_300_000_000, 0))
+  );
+}
+
+#[test]
+fn reference_with_modification_only() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "ref", "@1000000000", "@1100000000");
+  file_with_times(dir.path(), "a", "@1200000000", "@1300000000");
+  succeed(dir.path(), &["-m", "-r", "ref", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_200_000_000, 0), at(1_100_000_000, 0))
+  );
+}
+
+#[test]
+fn reference_with_a_relative_date_moves_both_times() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "ref", "@1000000000", "@1100000000");
+  succeed(dir.path(), &["-r", "ref", "-d", "-1 hour", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000 - 3600, 0), at(1_100_000_000 - 3600, 0))
+  );
+}
+
+#[test]
+fn missing_reference_is_an_error_and_creates_nothing() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-r", "nope", "a"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: failed to get attributes of 'nope': No such file or directory\n"
+  );
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn stamps_are_read_in_local_time() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-t", "202001020304.05", "a"]);
+  let time = at(1_577_934_245, 0);
+  assert_eq!(times(&dir.path().join("a")), (time, time));
+}
+
+#[test]
+fn stamps_with_two_digit_years() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-t", "6901010000", "a"]);
+  succeed(dir.path(), &["-t", "6801010000", "b"]);
+  assert_eq!(times(&dir.path().join("a")).1, at(-31_536_000, 0));
+  assert_eq!(times(&dir.path().join("b")).1, at(3_092_601_600, 0));
+}
+
+#[test]
+fn stamps_with_a_leap_second_roll_over() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["-t", "201612312359.60", "a"]);
+  assert_eq!(times(&dir.path().join("a")).1, at(1_483_228_800, 0));
+}
+
+#[test]
+fn stamp_with_modification_only() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "a", "@1000000000", "@1100000000");
+  succeed(dir.path(), &["-m", "-t", "202001020304.05", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_577_934_245, 0))
+  );
+}
+
+#[test]
+fn bad_stamps_are_errors() {
+  let dir = TempDir::new().unwrap();
+  for stamp in ["12", "202013010000", "202001010000.61", "2020010100x0"] {
+    let output = rtouch(dir.path(), &["-t", stamp, "a"]);
+    assert_eq!(output.status.code(), Some(1), "{}", stamp);
+    assert_eq!(
+      stderr(&output),
+      format!("rtouch: invalid date format '{}'\n", stamp)
+    );
+  }
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn bad_dates_are_errors_and_touch_nothing() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "a", "@1000000000", "@1000000000");
+  let output = rtouch(dir.path(), &["-d", "bogus", "a", "b"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(stderr(&output), "rtouch: invalid date format 'bogus'\n");
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+  assert!(!dir.path().join("b").exists());
+}
+
+#[test]
+fn stamp_and_date_conflict() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-t", "202001010000", "-d", "@1", "a"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(stderr(&output).starts_with(
+    "error: the argument '-t <STAMP>' cannot be used with '--date <DATE>'"
+  ));
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn stamp_and_reference_conflict() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("ref"), "").unwrap();
+  let output = rtouch(dir.path(), &["-t", "202001010000", "-r", "ref", "a"]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn no_files_is_a_usage_error() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &[]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(stderr(&output)
+    .starts_with("error: the following required arguments were not provided"));
+}
+
+#[test]
+fn missing_directories_are_an_error() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["nodir/a"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'nodir/a': No such file or directory\n"
+  );
+}
+
+#[test]
+fn files_in_a_regular_file_are_an_error() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("file"), "").unwrap();
+  let output = rtouch(dir.path(), &["file/a"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'file/a': Not a directory\n"
+  );
+}
+
+#[test]
+fn every_file_is_tried_after_a_failure() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-d", "@1000000000", "nodir/a", "b"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'nodir/a': No such file or directory\n"
+  );
+  assert_eq!(
+    times(&dir.path().join("b")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+}
+
+#[test]
+fn directories_are_updated() {
+  let dir = TempDir::new().unwrap();
+  fs::create_dir(dir.path().join("d")).unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "d"]);
+  assert_eq!(
+    times(&dir.path().join("d")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+}
+
+#[test]
+fn read_only_files_are_updated() {
+  let dir = TempDir::new().unwrap();
+  let file = dir.path().join("a");
+  fs::write(&file, "").unwrap();
+  fs::set_permissions(&file, Permissions::from_mode(0o444)).unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "a"]);
+  assert_eq!(times(&file), (at(1_000_000_000, 0), at(1_000_000_000, 0)));
+  assert_eq!(
+    fs::metadata(&file).unwrap().permissions().mode() & 0o777,
+    0o444
+  );
+}
+
+#[test]
+fn writable_files_of_other_users_are_updated_to_now() {
+  let dir = TempDir::new().unwrap();
+  let file = dir.path().join("rw");
+  succeed(dir.path(), &["-d", "@0", "rw"]);
+  fs::set_permissions(&file, Permissions::from_mode(0o666)).unwrap();
+  let Some(output) = rtouch_as_nobody(dir.path(), &["rw"]) else {
+    return;
+  };
+  assert_eq!(stderr(&output), "");
+  assert!(output.status.success());
+  assert!(modified(&file) > at(1_700_000_000, 0));
+  // Only the owner may set any other time.
+  let output = rtouch_as_nobody(dir.path(), &["-d", "@0", "rw"]).unwrap();
+  assert_eq!(output.status.code(), Some(1));
+  assert!(modified(&file) > at(1_700_000_000, 0));
+}
+
+#[test]
+fn symbolic_links_are_followed() {
+  let dir = TempDir::new().unwrap();
+  fs::write(dir.path().join("target"), "").unwrap();
+  symlink("target", dir.path().join("link")).unwrap();
+  // Following the link may update its own access time, but nothing else.
+  let (_, link) = link_times(&dir.path().join("link"));
+  succeed(dir.path(), &["-d", "@1000000000", "link"]);
+  assert_eq!(
+    times(&dir.path().join("target")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+  assert_eq!(link_times(&dir.path().join("link")).1, link);
+}
+
+#[test]
+fn no_dereference_updates_the_link_itself() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "target", "@1100000000", "@1100000000");
+  symlink("target", dir.path().join("link")).unwrap();
+  succeed(dir.path(), &["-h", "-d", "@1000000000", "link"]);
+  assert_eq!(
+    link_times(&dir.path().join("link")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+  assert_eq!(
+    times(&dir.path().join("target")),
+    (at(1_100_000_000, 0), at(1_100_000_000, 0))
+  );
+}
+
+#[test]
+fn dangling_links_create_their_target() {
+  let dir = TempDir::new().unwrap();
+  symlink("target", dir.path().join("link")).unwrap();
+  succeed(dir.path(), &["-d", "@1000000000", "link"]);
+  assert_eq!(
+    times(&dir.path().join("target")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+}
+
+#[test]
+fn dangling_links_are_updated_with_no_dereference() {
+  let dir = TempDir::new().unwrap();
+  symlink("target", dir.path().join("link")).unwrap();
+  succeed(dir.path(), &["-h", "-d", "@1000000000", "link"]);
+  assert_eq!(
+    link_times(&dir.path().join("link")),
+    (at(1_000_000_000, 0), at(1_000_000_000, 0))
+  );
+  assert!(!dir.path().join("target").exists());
+}
+
+#[test]
+fn no_dereference_never_creates_files() {
+  let dir = TempDir::new().unwrap();
+  let output = rtouch(dir.path(), &["-h", "missing"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: setting times of 'missing': No such file or directory\n"
+  );
+  assert!(!dir.path().join("missing").exists());
+  succeed(dir.path(), &["-c", "-h", "missing"]);
+}
+
+#[test]
+fn reference_links_are_followed_unless_no_dereference() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "target", "@1000000000", "@1100000000");
+  symlink("target", dir.path().join("link")).unwrap();
+  succeed(dir.path(), &["-r", "link", "a"]);
+  assert_eq!(
+    times(&dir.path().join("a")),
+    (at(1_000_000_000, 0), at(1_100_000_000, 0))
+  );
+  succeed(dir.path(), &["-h", "-d", "@1200000000", "link"]);
+  fs::write(dir.path().join("b"), "").unwrap();
+  succeed(dir.path(), &["-h", "-r", "link", "b"]);
+  assert_eq!(
+    times(&dir.path().join("b")),
+    (at(1_200_000_000, 0), at(1_200_000_000, 0))
+  );
+}
+
+#[test]
+fn octal_modes_are_given_exactly() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["--mode", "0646", "a"]);
+  assert_eq!(mode(&dir.path().join("a")), 0o646);
+}
+
+#[test]
+fn symbolic_modes_start_from_read_and_write() {
+  let dir = TempDir::new().unwrap();
+  succeed(dir.path(), &["--mode", "u=rw,go=", "a"]);
+  succeed(dir.path(), &["--mode", "u+x,g=u,o=r", "b"]);
+  assert_eq!(mode(&dir.path().join("a")), 0o600);
+  assert_eq!(mode(&dir.path().join("b")), 0o774);
+}
+
+#[test]
+fn existing_files_keep_their_mode() {
+  let dir = TempDir::new().unwrap();
+  let file = dir.path().join("a");
+  fs::write(&file, "").unwrap();
+  fs::set_permissions(&file, Permissions::from_mode(0o600)).unwrap();
+  succeed(dir.path(), &["--mode", "0777", "-d", "@1000000000", "a"]);
+  assert_eq!(mode(&file), 0o600);
+  assert_eq!(times(&file), (at(1_000_000_000, 0), at(1_000_000_000, 0)));
+}
+
+#[test]
+fn bad_modes_are_usage_errors() {
+  let dir = TempDir::new().unwrap();
+  for bad in ["0800", "17777", "u+q", "u", ""] {
+    let output = rtouch(dir.path(), &["--mode", bad, "a"]);
+    assert_eq!(output.status.code(), Some(2), "{}", bad);
+  }
+  assert!(!dir.path().join("a").exists());
+}
+
+#[test]
+fn exclusive_creates_missing_files() {
+  let dir = TempDir::new().unwrap();
+  succeed(
+    dir.path(),
+    &["--exclusive", "--mode", "0600", "-d", "@0", "a"],
+  );
+  let file = dir.path().join("a");
+  assert_eq!(mode(&file), 0o600);
+  assert_eq!(times(&file), (at(0, 0), at(0, 0)));
+}
+
+#[test]
+fn exclusive_fails_if_the_file_exists() {
+  let dir = TempDir::new().unwrap();
+  file_with_times(dir.path(), "a", "@1000000000", "@1000000000");
+  let file = dir.path().join("a");
+  fs::set_permissions(&file, Permissions::from_mode(0o640)).unwrap();
+  let output = rtouch(dir.path(), &["--exclusive", "--mode", "0600", "a"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(stderr(&output), "rtouch: cannot touch 'a': File exists\n");
+  assert_eq!(mode(&file), 0o640);
+  assert_eq!(times(&file), (at(1_000_000_000, 0), at(1_000_000_000, 0)));
+}
+
+#[test]
+fn exclusive_fails_on_dangling_links() {
+  let dir = TempDir::new().unwrap();
+  symlink("target", dir.path().join("link")).unwrap();
+  let output = rtouch(dir.path(), &["--exclusive", "link"]);
+  assert_eq!(output.status.code(), Some(1));
+  assert_eq!(
+    stderr(&output),
+    "rtouch: cannot touch 'link': File exists\n"
+  );
+  assert!(!dir.path().join("target").exists());
+}
+
+#[test]
+fn modes_are_given_to_the_targets_of_dangling_links() {
+  let dir = TempDir::new().unwrap();
+  symlink("target", dir.path().join("link")).unwrap();
+  let output = rtouch(dir.path(), &["-n", "-v", "--mode", "0600", "link"]);
+  assert!(stdout(&output).starts_with("rtouch: would create 'link'"));
+  assert!(!dir.path().join("target").exists());
+  succeed(dir.path(), &["--mode", "0600", "link"]);
+  assert_eq!(mode(&dir.path().join("target")), 0o600);
+}